@@ -0,0 +1,151 @@
+use git2::{BranchType, Repository, Status, StatusOptions};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Statuses of every changed path in the repository containing a listing
+pub struct GitStatuses {
+    workdir: PathBuf,
+    statuses: HashMap<PathBuf, Status>,
+}
+
+/// Canonical root of the work tree containing `path`, `None` when it isn't
+/// inside one
+pub fn workdir(path: &Path) -> Option<PathBuf> {
+    let repo = Repository::discover(path).ok()?;
+    fs::canonicalize(repo.workdir()?).ok()
+}
+
+impl GitStatuses {
+    /// Reads the statuses of the work tree at `workdir`, which takes a walk
+    /// over the whole repository
+    pub fn load(workdir: &Path) -> Option<Self> {
+        let repo = Repository::open(workdir).ok()?;
+        let workdir = workdir.to_path_buf();
+
+        // Otherwise an untracked directory is a single entry and the files
+        // inside it look unchanged
+        let mut options = StatusOptions::new();
+        options
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .include_ignored(false);
+        let statuses = repo
+            .statuses(Some(&mut options))
+            .ok()?
+            .iter()
+            .filter_map(|entry| Some((PathBuf::from(entry.path().ok()?), entry.status())))
+            .collect();
+
+        Some(GitStatuses { workdir, statuses })
+    }
+
+    /// Status of `path`, directories combine the statuses of their contents
+    pub fn status_of(&self, path: &Path) -> Status {
+        let Some(relative) = self.relative_path(path) else {
+            return Status::CURRENT;
+        };
+        self.statuses
+            .iter()
+            .filter(|(changed, _)| changed.starts_with(&relative))
+            .fold(Status::CURRENT, |acc, (_, status)| acc | *status)
+    }
+
+    fn relative_path(&self, path: &Path) -> Option<PathBuf> {
+        // Only the parent is canonicalized so symlinks keep their own status
+        let absolute = match (path.parent(), path.file_name()) {
+            (Some(parent), Some(name)) => {
+                let parent = if parent.as_os_str().is_empty() {
+                    Path::new(".")
+                } else {
+                    parent
+                };
+                fs::canonicalize(parent).ok()?.join(name)
+            }
+            _ => fs::canonicalize(path).ok()?,
+        };
+        absolute
+            .strip_prefix(&self.workdir)
+            .ok()
+            .map(Path::to_path_buf)
+    }
+}
+
+/// Branch and state of the repository containing a listing
+pub struct RepoSummary {
+    /// Branch name, or the short commit id when HEAD is detached
+    pub branch: String,
+    /// Commits ahead of and behind the upstream, if the branch has one
+    pub ahead_behind: Option<(usize, usize)>,
+    /// Whether anything is changed or untracked
+    pub dirty: bool,
+}
+
+impl RepoSummary {
+    /// Returns `None` when `path` isn't inside a git work tree
+    pub fn discover(path: &Path) -> Option<Self> {
+        let repo = Repository::discover(path).ok()?;
+        repo.workdir()?;
+
+        let (branch, ahead_behind) = match repo.head() {
+            Ok(head) if head.is_branch() => {
+                let name = head.shorthand().unwrap_or_default().to_string();
+                let ahead_behind = head.target().and_then(|local| {
+                    let upstream = repo
+                        .find_branch(&name, BranchType::Local)
+                        .ok()?
+                        .upstream()
+                        .ok()?
+                        .get()
+                        .target()?;
+                    repo.graph_ahead_behind(local, upstream).ok()
+                });
+                (name, ahead_behind)
+            }
+            Ok(head) => {
+                let id = head.target()?.to_string();
+                (id[..7.min(id.len())].to_string(), None)
+            }
+            // A fresh repository has a branch but no commit on it yet
+            Err(_) => {
+                let head = repo.find_reference("HEAD").ok()?;
+                let target = head.symbolic_target().ok().flatten()?;
+                let name = target.strip_prefix("refs/heads/").unwrap_or(target);
+                (name.to_string(), None)
+            }
+        };
+
+        let mut options = StatusOptions::new();
+        options.include_untracked(true).include_ignored(false);
+        let dirty = repo
+            .statuses(Some(&mut options))
+            .is_ok_and(|statuses| !statuses.is_empty());
+
+        Some(RepoSummary {
+            branch,
+            ahead_behind,
+            dirty,
+        })
+    }
+}
+
+/// Short status code in the style of `git status --short`
+pub fn status_code(status: Status) -> &'static str {
+    if status.is_conflicted() {
+        "U"
+    } else if status.intersects(Status::INDEX_MODIFIED | Status::WT_MODIFIED) {
+        "M"
+    } else if status.is_index_new() {
+        "A"
+    } else if status.intersects(Status::INDEX_DELETED | Status::WT_DELETED) {
+        "D"
+    } else if status.intersects(Status::INDEX_RENAMED | Status::WT_RENAMED) {
+        "R"
+    } else if status.is_wt_new() {
+        "?"
+    } else {
+        ""
+    }
+}
@@ -1,155 +1,2805 @@
-use chrono::{DateTime, Utc};
-use clap::Parser;
-use nix::unistd::{Uid, User};
-use owo_colors::OwoColorize;
+mod git;
+#[cfg(unix)]
+mod pager;
+#[cfg(unix)]
+mod picker;
+mod platform;
+mod theme;
+
+use chrono::{
+    format::{Item, StrftimeItems},
+    DateTime, Datelike, Local, Utc,
+};
+use clap::{CommandFactory, Parser, ValueEnum};
+use clap_complete::{generate, Shell};
+use git::{GitStatuses, RepoSummary};
+use glob::Pattern;
+use ignore::WalkBuilder;
+use lscolors::LsColors;
+use owo_colors::{OwoColorize, Style as TextStyle};
+#[cfg(unix)]
+use pager::Pager;
+use rayon::prelude::*;
+use serde::Serialize;
 use std::{
+    collections::{HashMap, HashSet},
+    ffi::{OsStr, OsString},
+    fmt::Display,
     fs,
-    os::unix::fs::{MetadataExt, PermissionsExt},
+    io::{BufRead, IsTerminal, Write},
     path::{Path, PathBuf},
+    process::ExitCode,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        LazyLock, Mutex, PoisonError,
+    },
+    thread,
+    time::{Duration, SystemTime},
 };
-use strum::Display;
 use tabled::{
+    builder::Builder,
+    grid::util::string::get_line_width,
     settings::{
-        object::{Columns, Rows},
-        Alignment, Color, Style,
+        location::ByColumnName, object::Rows, themes::Theme as TableTheme, Alignment, Color,
+        Remove, Style, Width as CellWidth,
     },
-    Table, Tabled,
 };
+use terminal_size::Width;
+use theme::{SizeThresholds, Theme};
+
+/// Number of entries and directories that couldn't be read, reported once the
+/// listing is done
+static ERRORS: AtomicUsize = AtomicUsize::new(0);
+
+/// Whether messages on stderr are colored, resolved from `--color` like
+/// the listing but for stderr
+static STDERR_COLOR: AtomicBool = AtomicBool::new(false);
+
+/// Names already resolved for a uid or gid, shared by every listed directory
+static USER_NAMES: LazyLock<Mutex<HashMap<u32, String>>> = LazyLock::new(Mutex::default);
+static GROUP_NAMES: LazyLock<Mutex<HashMap<u32, String>>> = LazyLock::new(Mutex::default);
+
+/// Sizes from `--du`, keyed by the path the directory was listed under
+static DIR_SIZES: LazyLock<Mutex<HashMap<PathBuf, u64>>> = LazyLock::new(Mutex::default);
+
+/// Statuses from `--git`, keyed by work tree so a recursive listing reads
+/// every repository only once
+static GIT_STATUSES: LazyLock<Mutex<HashMap<PathBuf, Option<GitStatuses>>>> =
+    LazyLock::new(Mutex::default);
+
+static THEME: LazyLock<Theme> = LazyLock::new(Theme::load);
+
+/// `LS_COLORS` as set up by `dircolors`, `None` when it isn't set
+static LS_COLORS: LazyLock<Option<LsColors>> = LazyLock::new(LsColors::from_env);
+
+#[derive(Debug, strum::Display, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+// Windows has no special files, only Unix produces every variant
+#[cfg_attr(windows, allow(dead_code))]
+enum FileType {
+    Dir,
+    File,
+    Symlink,
+    Fifo,
+    Socket,
+    BlockDevice,
+    CharDevice,
+}
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+enum SortKey {
+    #[default]
+    Name,
+    Size,
+    Time,
+    Type,
+    /// Natural order of numbers within names, e.g. `file2` before `file10`
+    Version,
+    /// Extension, then name, with extensionless entries first
+    Extension,
+}
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+enum OutputFormat {
+    #[default]
+    Table,
+    Json,
+    Csv,
+    /// GitHub flavored Markdown table
+    Markdown,
+}
+
+impl OutputFormat {
+    fn is_machine_readable(&self) -> bool {
+        matches!(self, OutputFormat::Json | OutputFormat::Csv)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+enum TimeField {
+    #[default]
+    Modified,
+    Accessed,
+    #[value(alias = "birth")]
+    Created,
+}
+
+impl TimeField {
+    fn header(&self) -> &'static str {
+        match self {
+            TimeField::Modified => "Modified",
+            TimeField::Accessed => "Accessed",
+            TimeField::Created => "Created",
+        }
+    }
+
+    fn of(&self, meta_data: &fs::Metadata) -> Option<SystemTime> {
+        match self {
+            TimeField::Modified => meta_data.modified(),
+            TimeField::Accessed => meta_data.accessed(),
+            TimeField::Created => meta_data.created(),
+        }
+        .ok()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+enum TimeStyle {
+    /// Day, month and time, e.g. ` 7 Jul 06:25`
+    #[default]
+    Default,
+    /// `2025-07-07 06:25:13`
+    Iso,
+    /// Day, month, year and time with seconds, e.g. ` 7 Jul 2025 06:25:13`
+    Full,
+    /// Time since the timestamp, e.g. `3 days ago`
+    Relative,
+}
+
+impl TimeStyle {
+    /// strftime format of the style, `None` for styles that aren't absolute
+    fn format(&self) -> Option<&'static str> {
+        match self {
+            TimeStyle::Default => Some("%e %b %H:%M"),
+            TimeStyle::Iso => Some("%Y-%m-%d %H:%M:%S"),
+            TimeStyle::Full => Some("%e %b %Y %H:%M:%S"),
+            TimeStyle::Relative => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SizeUnits {
+    Binary,
+    Si,
+    Bytes,
+}
+
+/// Condition on the permission bits from `--perm`, modelled after `find -perm`
+#[derive(Debug, Clone, Copy)]
+enum PermFilter {
+    /// Exactly these bits, e.g. `755`
+    Exact(u32),
+    /// At least these bits, e.g. `-o+w`
+    All(u32),
+    /// Any one of these bits, e.g. `/111`
+    Any(u32),
+}
+
+impl PermFilter {
+    fn matches(&self, mode: u32) -> bool {
+        match *self {
+            PermFilter::Exact(bits) => mode & 0o7777 == bits,
+            PermFilter::All(bits) => mode & bits == bits,
+            PermFilter::Any(bits) => bits == 0 || mode & bits != 0,
+        }
+    }
+}
+
+/// Border presets of `tabled`
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+enum TableStyle {
+    #[default]
+    Rounded,
+    Sharp,
+    Modern,
+    Extended,
+    Ascii,
+    Psql,
+    Dots,
+    Blank,
+    Empty,
+}
+
+/// How names with spaces, quotes or control characters are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+enum QuotingStyle {
+    /// `shell` on a terminal and `literal` otherwise
+    #[default]
+    Auto,
+    /// Names exactly as they are
+    Literal,
+    /// Single quoted so they can be pasted into a shell
+    Shell,
+    /// Special characters escaped with backslashes
+    Escape,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum GroupBy {
+    /// Directories, images, documents, code, archives and everything else
+    Type,
+    /// Today, yesterday, this week, this month and older
+    Date,
+}
+
+impl GroupBy {
+    fn section(&self, entry: &FileEntry) -> Section {
+        match self {
+            GroupBy::Type => Section::Type(Category::of(entry)),
+            GroupBy::Date => Section::Date(DateBucket::of(entry.modified_at)),
+        }
+    }
+}
+
+/// Heading a `--group-by` section is printed under
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Section {
+    Type(Category),
+    Date(DateBucket),
+}
+
+impl Display for Section {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Section::Type(category) => category.fmt(f),
+            Section::Date(bucket) => bucket.fmt(f),
+        }
+    }
+}
+
+/// Section of `--group-by type`, listed in declaration order
+#[derive(Debug, strum::Display, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Category {
+    Directories,
+    Images,
+    Documents,
+    Code,
+    Archives,
+    Other,
+}
+
+impl Category {
+    fn of(entry: &FileEntry) -> Self {
+        match entry.e_type {
+            FileType::Dir => return Category::Directories,
+            FileType::File => {}
+            _ => return Category::Other,
+        }
+        let extension = entry
+            .path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match extension.as_str() {
+            "png" | "jpg" | "jpeg" | "gif" | "svg" | "webp" | "bmp" | "ico" | "tif" | "tiff"
+            | "heic" | "avif" => Category::Images,
+            "pdf" | "txt" | "md" | "rst" | "tex" | "doc" | "docx" | "odt" | "rtf" | "xls"
+            | "xlsx" | "ods" | "ppt" | "pptx" | "odp" | "csv" | "epub" => Category::Documents,
+            "rs" | "py" | "js" | "mjs" | "cjs" | "ts" | "tsx" | "jsx" | "go" | "c" | "h"
+            | "cpp" | "cc" | "hpp" | "java" | "kt" | "swift" | "rb" | "php" | "lua" | "html"
+            | "css" | "scss" | "sh" | "bash" | "zsh" | "fish" | "json" | "toml" | "yaml"
+            | "yml" | "xml" | "sql" => Category::Code,
+            "zip" | "tar" | "gz" | "tgz" | "xz" | "bz2" | "zst" | "7z" | "rar" => {
+                Category::Archives
+            }
+            _ => Category::Other,
+        }
+    }
+}
+
+/// Section of `--group-by date`, newest first
+#[derive(Debug, strum::Display, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum DateBucket {
+    Today,
+    Yesterday,
+    #[strum(to_string = "This week")]
+    ThisWeek,
+    #[strum(to_string = "This month")]
+    ThisMonth,
+    Older,
+}
+
+impl DateBucket {
+    /// Buckets by calendar days in the local timezone rather than by age,
+    /// so "Yesterday" starts at midnight
+    fn of(time: Option<SystemTime>) -> Self {
+        let Some(time) = time else {
+            return DateBucket::Older;
+        };
+        let today = Local::now().date_naive();
+        let date = DateTime::<Local>::from(time).date_naive();
+        let days = (today - date).num_days();
+        if days <= 0 {
+            DateBucket::Today
+        } else if days == 1 {
+            DateBucket::Yesterday
+        } else if days < i64::from(today.weekday().num_days_from_monday()) + 1 {
+            DateBucket::ThisWeek
+        } else if date.year() == today.year() && date.month() == today.month() {
+            DateBucket::ThisMonth
+        } else {
+            DateBucket::Older
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+enum ColorWhen {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Debug, Clone)]
+struct FileEntry {
+    permissions: String,
+    owner: String,
+    uid: u32,
+    group: String,
+    name: String,
+    e_type: FileType,
+    modified: String,
+    path: PathBuf,
+    file_name: String,
+    size: u64,
+    modified_at: Option<SystemTime>,
+    /// The timestamp picked with `--time`, which the column shows and
+    /// `--sort time` orders by
+    shown_at: Option<SystemTime>,
+    mode: u32,
+    inode: u64,
+    links: u64,
+    blocks: u64,
+    /// Device number, only meaningful for block and character devices
+    rdev: u64,
+    /// Number of immediate children, only counted for directories with
+    /// `--count`
+    child_count: Option<usize>,
+    /// Type sniffed from the first bytes, only detected for files with
+    /// `--mime`
+    mime: Option<&'static str>,
+    git_status: Option<git2::Status>,
+    /// 1-based position in the final order, set once the listing is sorted
+    number: usize,
+    /// Set when the entry couldn't be stat-ed, only its name is known
+    unreadable: bool,
+}
+
+/// Column of the rendered table, formatting the raw fields of a `FileEntry`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Column {
+    /// Row number from `--number`
+    Number,
+    Inode,
+    Blocks,
+    Permissions,
+    Mode,
+    Links,
+    Size,
+    Count,
+    Owner,
+    Group,
+    /// Owner and group in one `user:group` cell
+    OwnerGroup,
+    Git,
+    Name,
+    Type,
+    Mime,
+    /// The timestamp picked with `--time`
+    #[value(alias = "time")]
+    Modified,
+}
+
+impl Column {
+    fn header(&self, cli: &Cli) -> &'static str {
+        match self {
+            Column::Number => "#",
+            Column::Inode => "Inode",
+            Column::Blocks => "Blocks",
+            Column::Permissions => "Permissions",
+            Column::Mode => "Mode",
+            Column::Links => "Links",
+            Column::Size => "Size",
+            Column::Count => "Count",
+            Column::Owner => "Owner",
+            Column::Group => "Group",
+            Column::OwnerGroup => "Owner:Group",
+            Column::Git => "Git",
+            Column::Name => "Name",
+            Column::Type => "Type",
+            Column::Mime => "MIME",
+            Column::Modified => cli.time.header(),
+        }
+    }
+
+    fn cell(&self, entry: &FileEntry, cli: &Cli) -> String {
+        if entry.unreadable && !matches!(self, Column::Name | Column::Number) {
+            return "?".to_string();
+        }
+        match self {
+            Column::Number => entry.number.to_string(),
+            Column::Inode => entry.inode.to_string(),
+            Column::Blocks => entry.blocks.to_string(),
+            Column::Permissions => entry.permissions.clone(),
+            Column::Mode => octal_mode(entry.mode),
+            Column::Links => entry.links.to_string(),
+            Column::Size => format_length(entry, cli),
+            Column::Count => entry
+                .child_count
+                .map_or("-".to_string(), |count| count.to_string()),
+            Column::Owner if cli.mine && platform::current_uid() == Some(entry.uid) => paint(
+                &entry.owner,
+                TextStyle::new().bright_green().bold(),
+                cli.use_color(),
+            ),
+            Column::Owner => entry.owner.clone(),
+            Column::Group => entry.group.clone(),
+            Column::OwnerGroup => {
+                let style = if cli.mine && platform::current_uid() == Some(entry.uid) {
+                    TextStyle::new().bright_green().bold()
+                } else {
+                    TextStyle::new().bright_yellow()
+                };
+                format!(
+                    "{}:{}",
+                    paint(&entry.owner, style, cli.use_color()),
+                    entry.group
+                )
+            }
+            Column::Git => format_git_status(entry.git_status, cli.use_color()),
+            Column::Name => entry.name.clone(),
+            Column::Type => entry.e_type.to_string(),
+            Column::Mime => entry.mime.unwrap_or("-").to_string(),
+            Column::Modified => entry.modified.clone(),
+        }
+    }
+}
+
+/// Uncolored view of a `FileEntry` used for machine readable output
+#[derive(Debug, Serialize)]
+struct RawEntry<'a> {
+    name: &'a str,
+    /// Path as it was listed, which tells apart entries of different
+    /// directories in one document
+    path: String,
+    #[serde(rename = "type")]
+    e_type: FileType,
+    size_bytes: u64,
+    owner: &'a str,
+    permissions_octal: String,
+    modified: Option<String>,
+}
+
+impl<'a> From<&'a FileEntry> for RawEntry<'a> {
+    fn from(entry: &'a FileEntry) -> Self {
+        RawEntry {
+            name: &entry.file_name,
+            path: entry.path.to_string_lossy().into_owned(),
+            e_type: entry.e_type,
+            size_bytes: entry.size,
+            owner: &entry.owner,
+            permissions_octal: octal_mode(entry.mode),
+            modified: entry
+                .modified_at
+                .map(|time| DateTime::<Utc>::from(time).to_rfc3339()),
+        }
+    }
+}
+
+impl FileEntry {
+    fn unreadable(path: &Path, file_name: &OsStr, cli: &Cli) -> Self {
+        FileEntry {
+            permissions: String::default(),
+            owner: String::default(),
+            uid: 0,
+            group: String::default(),
+            name: paint(
+                display_name(file_name, cli),
+                TextStyle::new().red(),
+                cli.use_color(),
+            ),
+            e_type: FileType::File,
+            modified: String::default(),
+            path: path.to_path_buf(),
+            file_name: file_name.to_string_lossy().into_owned(),
+            size: 0,
+            modified_at: None,
+            shown_at: None,
+            mode: 0,
+            inode: 0,
+            links: 0,
+            blocks: 0,
+            rdev: 0,
+            child_count: None,
+            mime: None,
+            git_status: None,
+            number: 0,
+            unreadable: true,
+        }
+    }
+
+    fn is_self_or_parent(&self) -> bool {
+        self.file_name == "." || self.file_name == ".."
+    }
+}
+
+/// File count, directory count and byte size of a listing
+#[derive(Debug, Default)]
+struct Totals {
+    files: usize,
+    dirs: usize,
+    bytes: u64,
+}
+
+impl Totals {
+    fn add(&mut self, files: &[FileEntry]) {
+        for file in files.iter().filter(|file| !file.is_self_or_parent()) {
+            if file.e_type == FileType::Dir {
+                self.dirs += 1;
+            } else {
+                self.files += 1;
+                self.bytes += file.size;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Parser)]
+#[command(
+    version,
+    about,
+    long_about = "ls -la command in a table like format in mimicing the nu ls"
+)]
+struct Cli {
+    /// Files or directories to list, defaults to the current directory
+    path: Vec<PathBuf>,
+
+    /// Read the paths to describe from stdin, one per line
+    #[arg(long, conflicts_with = "path")]
+    stdin: bool,
+
+    /// Comma separated columns to show, in order, instead of the default
+    /// layout
+    #[arg(long, value_enum, value_delimiter = ',')]
+    columns: Vec<Column>,
+
+    /// Key to sort the entries by
+    #[arg(long, value_enum, default_value_t = SortKey::Name)]
+    sort: SortKey,
+
+    /// Ignore case when sorting by name
+    #[arg(long)]
+    ignore_case: bool,
+
+    /// Reverse the order of the entries after sorting
+    #[arg(short, long)]
+    reverse: bool,
+
+    /// List directories before files, sorting each group separately
+    #[arg(long)]
+    group_directories_first: bool,
+
+    /// Split the listing into sections with a heading each
+    #[arg(long, value_enum, conflicts_with_all = ["oneline", "print0"])]
+    group_by: Option<GroupBy>,
+
+    /// Use powers of 1000 (kB, MB) instead of 1024 (KiB, MiB) for sizes
+    #[arg(long)]
+    si: bool,
+
+    /// Show exact sizes in bytes
+    #[arg(long, conflicts_with = "si")]
+    bytes: bool,
+
+    /// Append an indicator to names: `/` for directories, `*` for
+    /// executables, `@` for symlinks and `|` for FIFOs
+    #[arg(short = 'F', long)]
+    classify: bool,
+
+    /// Describe what symlinks point to instead of the links themselves,
+    /// still under the link's name
+    #[arg(short = 'L', long)]
+    dereference: bool,
+
+    /// Cut names longer than this many characters short with `…`
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    max_name_width: Option<u64>,
+
+    /// Wrap long names onto more lines so the table fits the terminal
+    #[arg(long, conflicts_with = "max_name_width")]
+    wrap: bool,
+
+    /// Show the absolute path of every entry instead of its name
+    #[arg(long)]
+    full_path: bool,
+
+    /// Append `/` to directory names
+    #[arg(short = 'p', long)]
+    slash: bool,
+
+    /// Prefix names with Nerd Font file type icons
+    #[arg(long)]
+    icons: bool,
+
+    /// Only show entries whose name matches the glob, can be repeated
+    #[arg(long, value_name = "PATTERN")]
+    glob: Vec<Pattern>,
+
+    /// Hide entries whose name matches the glob, can be repeated and wins
+    /// over `--glob`
+    #[arg(short = 'I', long, value_name = "PATTERN")]
+    ignore: Vec<Pattern>,
+
+    /// Read more `--ignore` patterns from a file, one per line, can be
+    /// repeated
+    #[arg(long, value_name = "PATH")]
+    ignore_file: Vec<PathBuf>,
+
+    /// Only show files with one of the comma separated extensions,
+    /// directories are always kept so they can still be navigated
+    #[arg(long, value_name = "EXT", value_delimiter = ',')]
+    ext: Vec<String>,
+
+    /// Show owner and group in a single `user:group` column
+    #[arg(long)]
+    owner_group: bool,
+
+    /// Highlight the owner of entries that belong to the current user
+    #[arg(long)]
+    mine: bool,
+
+    /// Only list entries owned by this user name or uid
+    #[arg(long, value_name = "USER", value_parser = parse_owner)]
+    owner: Option<u32>,
+
+    /// Only list entries whose permissions match: an octal mode like `755`,
+    /// `-MODE` for at least those bits, `/MODE` for any of them, either as
+    /// octal or symbolic like `-o+w`, or one of `world-writable`, `setuid`,
+    /// `setgid` and `sticky`
+    #[arg(long, value_name = "SPEC", value_parser = parse_perm, allow_hyphen_values = true)]
+    perm: Option<PermFilter>,
+
+    /// Only list entries of at least this size, e.g. `10k`, `5M` or `1GB`;
+    /// directories are kept unless `--du` sizes them
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    min_size: Option<u64>,
+
+    /// Only list entries of at most this size, same format as `--min-size`
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    max_size: Option<u64>,
+
+    /// Only list entries modified within this long, e.g. `30m`, `7h` or
+    /// `2d`, in s, m, h, d or w units
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    newer_than: Option<Duration>,
+
+    /// Only list entries modified longer ago than this, same format as
+    /// `--newer-than`
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    older_than: Option<Duration>,
+
+    /// Only list directories
+    #[arg(long, conflicts_with = "files_only")]
+    dirs_only: bool,
+
+    /// Only list entries that aren't directories
+    #[arg(long)]
+    files_only: bool,
+
+    /// Hide entries ignored by git
+    #[arg(long)]
+    gitignore: bool,
+
+    /// Show the git status of each entry
+    #[arg(long)]
+    git: bool,
+
+    /// Show the inode number of each entry
+    #[arg(short, long)]
+    inode: bool,
+
+    /// Mark entries with extended attributes with `@` after the permissions
+    #[arg(long)]
+    xattr: bool,
+
+    /// Show the permissions in octal next to the symbolic ones
+    #[arg(long)]
+    octal: bool,
+
+    /// Show the number of 512 byte blocks allocated to each entry
+    #[arg(long)]
+    blocks: bool,
+
+    /// Show the number of hard links to each entry
+    #[arg(long)]
+    links: bool,
+
+    /// Show numeric user and group ids instead of names
+    #[arg(short, long)]
+    numeric: bool,
+
+    /// Show hidden entries, including `.` and `..`
+    #[arg(short, long)]
+    all: bool,
+
+    /// Show hidden entries, but not `.` and `..`. `-a` wins over it
+    #[arg(short = 'A', long)]
+    almost_all: bool,
+
+    /// List subdirectories recursively
+    #[arg(short = 'R', long)]
+    recursive: bool,
+
+    /// Maximum depth to descend to when listing recursively
+    #[arg(long, value_name = "N")]
+    depth: Option<usize>,
+
+    /// Show subdirectories as an indented tree
+    #[arg(long)]
+    tree: bool,
+
+    /// Show permissions and size next to each name in tree mode
+    #[arg(short, long, requires = "tree")]
+    long: bool,
+
+    /// Timestamp to show in the time column and sort by
+    #[arg(long, value_enum, default_value_t = TimeField::Modified)]
+    time: TimeField,
+
+    /// How to format timestamps
+    #[arg(long, value_enum, default_value_t = TimeStyle::Default)]
+    time_style: TimeStyle,
+
+    /// Custom strftime format for timestamps, takes precedence over
+    /// `--time-style`
+    #[arg(long, value_name = "FORMAT", value_parser = parse_time_format)]
+    time_format: Option<String>,
+
+    /// Show timestamps in UTC instead of the local timezone
+    #[arg(long)]
+    utc: bool,
+
+    /// Print the total size and number of files and directories listed
+    #[arg(long)]
+    total: bool,
+
+    /// Lay out just the names in columns fitting the terminal width
+    #[arg(long)]
+    grid: bool,
+
+    /// Print just the name of each entry, one per line
+    #[arg(short = '1', long, conflicts_with = "grid")]
+    oneline: bool,
+
+    /// Print raw names separated by NUL bytes, for `xargs -0`
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "grid", "oneline", "recursive", "tree", "total", "df", "summary", "format",
+            "git_header"
+        ]
+    )]
+    print0: bool,
+
+    /// Exit on the first entry that can't be read instead of listing it
+    /// with placeholders
+    #[arg(long)]
+    strict: bool,
+
+    /// Color names with the built in scheme even when `LS_COLORS` is set
+    #[arg(long)]
+    no_lscolors: bool,
+
+    /// Leave out the header row of the table
+    #[arg(long)]
+    no_header: bool,
+
+    /// Border style of the table
+    #[arg(long, value_enum, default_value_t = TableStyle::Rounded)]
+    style: TableStyle,
+
+    /// Write the listing to a file instead of stdout, without colors unless
+    /// `--color=always` is passed
+    #[arg(short, long, value_name = "PATH")]
+    output: Option<PathBuf>,
+
+    /// Fuzzy filter the listing by typing and print the picked paths. Tab
+    /// marks several, enter picks and escape cancels
+    #[arg(
+        long,
+        conflicts_with_all = ["recursive", "tree", "watch", "stdin", "grid", "oneline", "print0"]
+    )]
+    interactive: bool,
+
+    /// Clear the screen and list again every `--interval` seconds
+    #[arg(long, conflicts_with = "stdin")]
+    watch: bool,
+
+    /// Seconds between refreshes in `--watch` mode
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = 2,
+        requires = "watch",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    interval: u64,
+
+    /// Page the output through `$PAGER`, or `less`, even when stdout isn't
+    /// a terminal or the listing fits on the screen
+    #[arg(long)]
+    pager: bool,
+
+    /// Never page the output
+    #[arg(long, overrides_with = "pager")]
+    no_pager: bool,
+
+    /// Show at most this many entries of each listing
+    #[arg(long, value_name = "N")]
+    limit: Option<usize>,
+
+    /// Skip this many entries at the start of each listing
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    offset: usize,
+
+    /// Show the N most recently modified entries, short for `--sort time
+    /// --reverse --limit N`
+    #[arg(long, value_name = "N", conflicts_with_all = ["sort", "reverse", "limit"])]
+    recent: Option<usize>,
+
+    /// Show the N largest files and their total size, short for `--sort
+    /// size --reverse --limit N --files-only --total`
+    #[arg(
+        long,
+        value_name = "N",
+        conflicts_with_all = ["sort", "reverse", "limit", "recent", "dirs_only"]
+    )]
+    largest: Option<usize>,
+
+    /// Show the total size of the files inside each directory, walking it
+    /// recursively
+    #[arg(long)]
+    du: bool,
+
+    /// Show how many entries each directory directly contains
+    #[arg(long)]
+    count: bool,
+
+    /// Prefix every row with its position in the listing
+    #[arg(long)]
+    number: bool,
+
+    /// Flag directories that have nothing in them
+    #[arg(long)]
+    mark_empty: bool,
+
+    /// Show the MIME type of files, detected from their contents
+    #[arg(long)]
+    mime: bool,
+
+    /// Print the branch and state of the repository above the listing
+    #[arg(long)]
+    git_header: bool,
+
+    /// Print how many files and directories are listed and their size
+    /// above the listing
+    #[arg(long)]
+    summary: bool,
+
+    /// Show the free and total space of the filesystem after the listing
+    #[arg(long)]
+    df: bool,
+
+    /// Output format of the listing
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    format: OutputFormat,
+
+    /// When to make names clickable links to the files, `auto` only does
+    /// so in terminals known to support them
+    #[arg(
+        long,
+        value_name = "WHEN",
+        value_enum,
+        num_args = 0..=1,
+        default_value_t = ColorWhen::Never,
+        default_missing_value = "always",
+        require_equals = true
+    )]
+    hyperlink: ColorWhen,
+
+    /// How to quote names with special characters
+    #[arg(long, value_enum, default_value_t = QuotingStyle::Auto)]
+    quoting_style: QuotingStyle,
+
+    /// When to color the output, `auto` also respects `NO_COLOR` and
+    /// `TERM=dumb`
+    #[arg(long, value_enum, default_value_t = ColorWhen::Auto)]
+    color: ColorWhen,
+
+    /// Print a completion script for the given shell and exit
+    #[arg(long, value_name = "SHELL", hide = true)]
+    generate_completions: Option<Shell>,
+}
+
+impl Cli {
+    fn use_color(&self) -> bool {
+        self.color == ColorWhen::Always
+    }
+
+    fn size_units(&self) -> SizeUnits {
+        if self.bytes {
+            SizeUnits::Bytes
+        } else if self.si {
+            SizeUnits::Si
+        } else {
+            SizeUnits::Binary
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    platform::default_sigpipe();
+    let mut cli = Cli::parse();
+    // Errors can come up before `--color` is resolved for the listing, and
+    // stderr may be a terminal when stdout isn't
+    STDERR_COLOR.store(
+        match cli.color {
+            ColorWhen::Always => true,
+            ColorWhen::Never => false,
+            ColorWhen::Auto => std::io::stderr().is_terminal() && !color_disabled_by_env(),
+        },
+        Ordering::Relaxed,
+    );
+    if let Some(shell) = cli.generate_completions {
+        generate(shell, &mut Cli::command(), "nuls", &mut std::io::stdout());
+        return ExitCode::SUCCESS;
+    }
+    for path in &cli.ignore_file {
+        match read_ignore_file(path) {
+            Ok(patterns) => cli.ignore.extend(patterns),
+            Err(err) => {
+                eprintln!("{}", error_text(format!("{}: {}", path.display(), err)));
+                return ExitCode::from(2);
+            }
+        }
+    }
+    // Redirected before `auto` is resolved below, which then sees a file
+    // rather than a terminal
+    if let Some(path) = &cli.output {
+        if let Err(err) = redirect_stdout(path) {
+            eprintln!("{}", error_text(format!("{}: {}", path.display(), err)));
+            return ExitCode::from(2);
+        }
+    }
+    // Resolve `auto` once up front so the rest of the code only has to check
+    // for `always`
+    if cli.color == ColorWhen::Auto {
+        // The picker draws on the terminal while stdout only gets the paths
+        let terminal = std::io::stdout().is_terminal() || cli.interactive;
+        cli.color = if terminal && !color_disabled_by_env() {
+            ColorWhen::Always
+        } else {
+            ColorWhen::Never
+        };
+    }
+    if cli.hyperlink == ColorWhen::Auto {
+        cli.hyperlink = if supports_hyperlinks() {
+            ColorWhen::Always
+        } else {
+            ColorWhen::Never
+        };
+    }
+    // Like `ls`, piped names stay literal so other programs get them as is
+    if cli.quoting_style == QuotingStyle::Auto {
+        cli.quoting_style = if std::io::stdout().is_terminal() {
+            QuotingStyle::Shell
+        } else {
+            QuotingStyle::Literal
+        };
+    }
+    cli.path = cli.path.iter().map(|path| expand_path(path)).collect();
+    if let Some(count) = cli.recent {
+        cli.sort = SortKey::Time;
+        cli.reverse = true;
+        cli.limit = Some(count);
+    }
+    if let Some(count) = cli.largest {
+        cli.sort = SortKey::Size;
+        cli.reverse = true;
+        cli.limit = Some(count);
+        cli.files_only = true;
+        cli.total = true;
+    }
+    // Escape codes would end up verbatim in the rendered document
+    if matches!(cli.format, OutputFormat::Markdown) {
+        cli.color = ColorWhen::Never;
+    }
+
+    // A redraw loop has nothing to scroll. Collecting stdout to see whether
+    // it fits on the screen needs Unix file descriptors
+    #[cfg(unix)]
+    let pager = if !cli.no_pager
+        && !cli.watch
+        && !cli.interactive
+        && (cli.pager || std::io::stdout().is_terminal())
+    {
+        Pager::start(cli.pager)
+    } else {
+        None
+    };
+
+    // Like `ls`, entries that couldn't be read are a minor problem while a
+    // path that couldn't be listed at all is a serious one
+    let result = if cli.watch {
+        watch(&cli)
+    } else if cli.stdin {
+        list_stdin(&cli).map(|()| 0)
+    } else {
+        list_paths(&cli)
+    };
+    #[cfg(unix)]
+    if let Some(pager) = pager {
+        pager.finish();
+    }
+    // `--strict` stops at the first entry that couldn't be read
+    let mut status = result.unwrap_or_else(|err| {
+        eprintln!("{}", error_text(err));
+        1
+    });
+
+    let errors = ERRORS.load(Ordering::Relaxed);
+    if errors > 0 {
+        eprintln!(
+            "{}",
+            error_text(format!(
+                "{} {} could not be read",
+                errors,
+                if errors == 1 { "entry" } else { "entries" }
+            ))
+        );
+        status = status.max(1);
+    }
+    ExitCode::from(status)
+}
+
+/// Lists every path given on the command line, returning `ls`'s exit status
+/// for them
+fn list_paths(cli: &Cli) -> std::io::Result<u8> {
+    let color = cli.use_color();
+    let paths = if cli.path.is_empty() {
+        vec![PathBuf::from(".")]
+    } else {
+        cli.path.clone()
+    };
+    let machine_readable = cli.format.is_machine_readable();
+    // Every path goes into a single document so the output still parses,
+    // and into a single picker so only the picked paths are printed
+    let collected = machine_readable || cli.interactive;
+    // Recursive listings and trees already print a header for every
+    // directory
+    let show_headers = paths.len() > 1 && !cli.recursive && !cli.tree && !cli.print0 && !collected;
+
+    let mut status = 0;
+    let mut documented = Vec::new();
+    for (i, path) in paths.iter().enumerate() {
+        // Not followed, so a broken symlink is still listed as a link
+        match fs::symlink_metadata(path) {
+            Ok(_) if collected => documented.extend(document_entries(path, cli)?),
+            Ok(_) => {
+                if i > 0 && (show_headers || cli.tree) {
+                    println!();
+                }
+                if show_headers {
+                    println!(
+                        "{}",
+                        paint(
+                            format!("{}:", path.display()),
+                            TextStyle::new().bold(),
+                            color
+                        )
+                    );
+                }
+                list_path(path, cli)?;
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                eprintln!(
+                    "{}",
+                    error_text(format!("{}: Path does not exist", path.display()))
+                );
+                status = 2;
+            }
+            Err(err) => {
+                eprintln!("{}", error_text(format!("{}: {}", path.display(), err)));
+                status = 2;
+            }
+        }
+    }
+    if cli.interactive {
+        pick_entries(&documented, cli);
+    } else if machine_readable {
+        print_entries(&documented, cli);
+    }
+    Ok(status)
+}
+
+/// Entries of `path` for a JSON or CSV document or the picker, with
+/// everything below it for `--recursive` and `--tree`
+fn document_entries(path: &Path, cli: &Cli) -> std::io::Result<Vec<FileEntry>> {
+    if !path.is_dir() {
+        return get_file(path, cli);
+    }
+    if !cli.recursive && !cli.tree {
+        return get_files(path, cli);
+    }
+    Ok(walk_dirs(path, cli)?
+        .into_iter()
+        .flat_map(|listing| listing.files)
+        .collect())
+}
+
+/// One directory of a `--recursive` or `--tree` walk
+struct Listing {
+    path: PathBuf,
+    files: Vec<FileEntry>,
+}
+
+/// Every directory from `path` down in the order they're listed, read once
+/// so the summary, the listing and the footer all come from the same walk
+fn walk_dirs(path: &Path, cli: &Cli) -> std::io::Result<Vec<Listing>> {
+    let mut listings = Vec::new();
+    walk_into(path, cli, 0, &mut HashSet::new(), &mut listings)?;
+    Ok(listings)
+}
+
+fn walk_into(
+    path: &Path,
+    cli: &Cli,
+    depth: usize,
+    visited: &mut HashSet<PathBuf>,
+    listings: &mut Vec<Listing>,
+) -> std::io::Result<()> {
+    // Symlinked directories can point back up the tree, so every directory
+    // is only listed once by its canonical path
+    if let Ok(canonical) = fs::canonicalize(path) {
+        if !visited.insert(canonical) {
+            return Ok(());
+        }
+    }
+    let files = get_files(path, cli)?;
+    let nested: Vec<PathBuf> = if cli.depth.is_some_and(|max| depth >= max) {
+        Vec::new()
+    } else {
+        files
+            .iter()
+            .filter(|file| file.e_type == FileType::Dir && !file.is_self_or_parent())
+            .map(|file| file.path.clone())
+            .collect()
+    };
+    listings.push(Listing {
+        path: path.to_path_buf(),
+        files,
+    });
+    for dir in nested {
+        walk_into(&dir, cli, depth + 1, visited, listings)?;
+    }
+    Ok(())
+}
+
+fn watch(cli: &Cli) -> std::io::Result<u8> {
+    loop {
+        // Clear the screen and move the cursor home before every redraw
+        print!("\x1b[2J\x1b[H");
+        list_paths(cli)?;
+        thread::sleep(Duration::from_secs(cli.interval));
+    }
+}
+
+/// `NO_COLOR` set to a non-empty value, see <https://no-color.org>, or a
+/// terminal that can't show escape codes
+fn color_disabled_by_env() -> bool {
+    std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+        || std::env::var_os("TERM").is_some_and(|term| term == "dumb")
+}
+
+/// Points stdout at a new file at `path`, so everything after this is
+/// printed into it
+#[cfg(unix)]
+fn redirect_stdout(path: &Path) -> std::io::Result<()> {
+    let file = fs::File::create(path)?;
+    nix::unistd::dup2_stdout(&file)?;
+    Ok(())
+}
+
+#[cfg(windows)]
+fn redirect_stdout(_path: &Path) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "--output needs Unix file descriptors",
+    ))
+}
+
+/// Expands `~` and `$VAR` in paths that didn't go through a shell, keeping
+/// the path as it is when a variable isn't set
+fn expand_path(path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        return path.to_path_buf();
+    }
+    path.to_str()
+        .and_then(|path| shellexpand::full(path).ok())
+        .map_or_else(|| path.to_path_buf(), |path| PathBuf::from(path.as_ref()))
+}
+
+/// Describes the paths read from stdin, one per line, in a single listing
+fn list_stdin(cli: &Cli) -> std::io::Result<()> {
+    let mut data = Vec::default();
+    for line in std::io::stdin().lock().lines().map_while(Result::ok) {
+        if line.is_empty() {
+            continue;
+        }
+        let path = expand_path(Path::new(&line));
+        // Paths piped in may be stale by now, they shouldn't stop the rest
+        if fs::symlink_metadata(&path).is_err() {
+            eprintln!(
+                "{}",
+                error_text(format!("{}: Path does not exist, skipping", path.display()))
+            );
+            continue;
+        }
+        data.push(read_entry(&path, path.as_os_str(), cli)?);
+    }
+
+    if cli.git {
+        add_git_statuses(&mut data, Path::new("."));
+    }
+    order_entries(&mut data, cli);
+    number_entries(&mut data);
+    let mut totals = Totals::default();
+    totals.add(shown_entries(&data, cli));
+    print_summary(&totals, cli);
+    print_entries(&data, cli);
+    print_total(&totals, cli);
+    Ok(())
+}
+
+fn list_path(path: &Path, cli: &Cli) -> std::io::Result<()> {
+    print_git_header(path, cli);
+    if (cli.tree || cli.recursive) && path.is_dir() {
+        let listings = walk_dirs(path, cli)?;
+        let mut totals = Totals::default();
+        for listing in &listings {
+            // Trees show every entry, `--limit` only cuts the tables short
+            if cli.tree {
+                totals.add(&listing.files);
+            } else {
+                totals.add(shown_entries(&listing.files, cli));
+            }
+        }
+        print_summary(&totals, cli);
+        if cli.tree {
+            println!(
+                "{}",
+                paint(
+                    path.display(),
+                    TextStyle::new().blue().bold(),
+                    cli.use_color()
+                )
+            );
+            let walked = listings
+                .iter()
+                .map(|listing| (listing.path.as_path(), listing.files.as_slice()))
+                .collect();
+            print_tree(path, cli, "", &walked);
+        } else {
+            list_recursive(&listings, cli);
+        }
+        print_total(&totals, cli);
+    } else {
+        // `read_dir` on a file yields nothing, so a file argument is
+        // described by a single row of its own
+        let files = if path.is_dir() {
+            get_files(path, cli)?
+        } else {
+            get_file(path, cli)?
+        };
+        let mut totals = Totals::default();
+        totals.add(shown_entries(&files, cli));
+        print_summary(&totals, cli);
+        print_entries(&files, cli);
+        print_total(&totals, cli);
+    }
+    print_disk_space(path, cli);
+    Ok(())
+}
+
+#[cfg(unix)]
+fn pick_entries(files: &[FileEntry], cli: &Cli) {
+    let names: Vec<String> = files.iter().map(|file| file.file_name.clone()).collect();
+    let picked = picker::pick(&names, |view| {
+        let rows: Vec<FileEntry> = view
+            .shown
+            .iter()
+            .enumerate()
+            .map(|(row, &index)| {
+                let mut file = files[index].clone();
+                let marker = format!(
+                    "{}{} ",
+                    if row == view.cursor { '>' } else { ' ' },
+                    if view.marked.contains(&index) {
+                        '*'
+                    } else {
+                        ' '
+                    }
+                );
+                file.name = paint(marker, TextStyle::new().bold(), cli.use_color()) + &file.name;
+                file
+            })
+            .collect();
+        render_table(&rows, cli)
+    });
+    match picked {
+        Ok(Some(picked)) => {
+            for index in picked {
+                println!("{}", files[index].path.display());
+            }
+        }
+        // Like `fzf`, so scripts can tell a cancelled pick from an empty one
+        Ok(None) => std::process::exit(130),
+        Err(err) => {
+            eprintln!(
+                "{}",
+                error_text(format!("Could not open the terminal: {}", err))
+            );
+            std::process::exit(2);
+        }
+    }
+}
+
+#[cfg(windows)]
+fn pick_entries(_files: &[FileEntry], _cli: &Cli) {
+    eprintln!("{}", error_text("--interactive needs a Unix terminal"));
+    std::process::exit(2);
+}
+
+fn print_git_header(path: &Path, cli: &Cli) {
+    if !cli.git_header || cli.format.is_machine_readable() {
+        return;
+    }
+    let Some(summary) = RepoSummary::discover(path) else {
+        return;
+    };
+    let color = cli.use_color();
+    let mut header = paint(&summary.branch, TextStyle::new().magenta().bold(), color);
+    if let Some((ahead, behind)) = summary.ahead_behind {
+        if ahead > 0 {
+            header.push_str(&format!(" ↑{}", ahead));
+        }
+        if behind > 0 {
+            header.push_str(&format!(" ↓{}", behind));
+        }
+    }
+    header.push(' ');
+    header.push_str(&if summary.dirty {
+        paint("dirty", TextStyle::new().red(), color)
+    } else {
+        paint("clean", TextStyle::new().green(), color)
+    });
+    println!("{}", header);
+}
+
+fn print_disk_space(path: &Path, cli: &Cli) {
+    if !cli.df || cli.format.is_machine_readable() {
+        return;
+    }
+    let Some(space) = platform::disk_space(path) else {
+        return;
+    };
+    // Like `df`, the reserved blocks count as neither used nor free
+    let percent = space.used as f64 / (space.used + space.available).max(1) as f64 * 100.0;
+    println!(
+        "{} free of {} ({:.0}% used)",
+        paint(
+            human_size(space.available, cli.size_units()),
+            TextStyle::new().bright_yellow(),
+            cli.use_color()
+        ),
+        human_size(space.total, cli.size_units()),
+        percent.ceil()
+    );
+}
+
+fn print_total(totals: &Totals, cli: &Cli) {
+    // A footer would break machine readable output
+    if !cli.total || cli.format.is_machine_readable() {
+        return;
+    }
+    println!("{}", format_totals(totals, cli));
+}
+
+fn print_summary(totals: &Totals, cli: &Cli) {
+    if !cli.summary || cli.format.is_machine_readable() {
+        return;
+    }
+    let (size, counts) = totals_parts(totals, cli);
+    println!("{}, {} total", counts, size);
+}
+
+fn format_totals(totals: &Totals, cli: &Cli) -> String {
+    let (size, counts) = totals_parts(totals, cli);
+    format!("{} in {}", size, counts)
+}
+
+/// The colored size and the `3 files, 1 directory` counts, which the
+/// footer and the `--summary` header put in a different order
+fn totals_parts(totals: &Totals, cli: &Cli) -> (String, String) {
+    let size = paint(
+        human_size(totals.bytes, cli.size_units()),
+        TextStyle::new().bright_yellow(),
+        cli.use_color(),
+    );
+    let counts = format!(
+        "{} {}, {} {}",
+        totals.files,
+        if totals.files == 1 { "file" } else { "files" },
+        totals.dirs,
+        if totals.dirs == 1 {
+            "directory"
+        } else {
+            "directories"
+        },
+    );
+    (size, counts)
+}
+
+/// The window of a sorted listing picked with `--offset` and `--limit`
+fn shown_entries<'a>(files: &'a [FileEntry], cli: &Cli) -> &'a [FileEntry] {
+    let start = cli.offset.min(files.len());
+    let end = cli.limit.map_or(files.len(), |limit| {
+        start.saturating_add(limit).min(files.len())
+    });
+    &files[start..end]
+}
+
+fn print_entries(files: &[FileEntry], cli: &Cli) {
+    let shown = shown_entries(files, cli);
+    let hidden = files.len() - shown.len();
+    let files = shown;
+
+    match (cli.group_by, cli.format) {
+        // `order_entries` already put every section's entries together
+        (Some(group_by), OutputFormat::Table) => {
+            let sections = files.chunk_by(|a, b| group_by.section(a) == group_by.section(b));
+            for (i, section) in sections.enumerate() {
+                if i > 0 {
+                    println!();
+                }
+                println!(
+                    "{}",
+                    paint(
+                        group_by.section(&section[0]),
+                        TextStyle::new().bold(),
+                        cli.use_color()
+                    )
+                );
+                print_section(section, cli);
+            }
+        }
+        _ => print_section(files, cli),
+    }
+    // Goes to stderr so it can't end up in piped names or documents
+    if hidden > 0 {
+        eprintln!(
+            "{}",
+            paint(
+                format!(
+                    "{} {} not shown",
+                    hidden,
+                    if hidden == 1 { "entry" } else { "entries" }
+                ),
+                TextStyle::new().bright_black(),
+                STDERR_COLOR.load(Ordering::Relaxed)
+            )
+        );
+    }
+}
+
+fn print_section(files: &[FileEntry], cli: &Cli) {
+    match cli.format {
+        OutputFormat::Table if cli.grid => print_grid(files),
+        OutputFormat::Table if cli.oneline => print_oneline(files),
+        OutputFormat::Table if cli.print0 => print_null_separated(files),
+        OutputFormat::Table => print_table(files, cli),
+        OutputFormat::Json => print_json(files),
+        OutputFormat::Csv => print_csv(files),
+        OutputFormat::Markdown => print_markdown(files, cli),
+    }
+}
+
+fn terminal_width() -> usize {
+    terminal_size::terminal_size()
+        .map(|(Width(width), _)| width as usize)
+        .unwrap_or(80)
+}
+
+fn print_grid(files: &[FileEntry]) {
+    const GAP: usize = 2;
+    let width = terminal_width();
+    let widths: Vec<usize> = files
+        .iter()
+        .map(|file| get_line_width(&file.name))
+        .collect();
+    // Names wider than half the terminal would leave most of it empty, so
+    // they're put on a row of their own instead of widening every column
+    let column_width = widths
+        .iter()
+        .copied()
+        .filter(|name_width| name_width + GAP <= width / 2)
+        .max()
+        .unwrap_or(0)
+        + GAP;
+    let columns = (width / column_width).max(1);
+
+    let mut row = String::new();
+    let mut in_row = 0;
+    for (file, name_width) in files.iter().zip(widths) {
+        if name_width + GAP > column_width {
+            if in_row > 0 {
+                println!("{}", row.trim_end());
+                row.clear();
+                in_row = 0;
+            }
+            println!("{}", file.name);
+            continue;
+        }
+
+        row.push_str(&file.name);
+        row.push_str(&" ".repeat(column_width - name_width));
+        in_row += 1;
+        if in_row == columns {
+            println!("{}", row.trim_end());
+            row.clear();
+            in_row = 0;
+        }
+    }
+    if in_row > 0 {
+        println!("{}", row.trim_end());
+    }
+}
+
+fn print_oneline(files: &[FileEntry]) {
+    for file in files {
+        println!("{}", file.name);
+    }
+}
+
+fn print_null_separated(files: &[FileEntry]) {
+    let mut stdout = std::io::stdout().lock();
+    for file in files {
+        // The stored name is lossy, so it's taken from the path instead to
+        // pass non UTF-8 names through untouched
+        let name = match file.path.file_name() {
+            Some(name) if !file.is_self_or_parent() => name,
+            _ => OsStr::new(&file.file_name),
+        };
+        if let Err(err) = stdout
+            .write_all(name.as_encoded_bytes())
+            .and_then(|_| stdout.write_all(b"\0"))
+        {
+            eprintln!("{}", error_text(format!("Error writing names: {}", err)));
+            return;
+        }
+    }
+}
+
+fn print_csv(files: &[FileEntry]) {
+    let mut writer = csv::Writer::from_writer(std::io::stdout());
+    for file in files {
+        if let Err(err) = writer.serialize(RawEntry::from(file)) {
+            eprintln!("{}", error_text(format!("Error writing csv: {}", err)));
+            return;
+        }
+    }
+    if let Err(err) = writer.flush() {
+        eprintln!("{}", error_text(format!("Error writing csv: {}", err)));
+    }
+}
+
+fn print_json(files: &[FileEntry]) {
+    let entries: Vec<RawEntry> = files.iter().map(RawEntry::from).collect();
+    match serde_json::to_string_pretty(&entries) {
+        Ok(json) => println!("{}", json),
+        Err(err) => eprintln!(
+            "{}",
+            error_text(format!("Error serializing entries: {}", err))
+        ),
+    }
+}
+
+fn table_columns(files: &[FileEntry], cli: &Cli) -> Vec<Column> {
+    let mut columns = if cli.columns.is_empty() {
+        [
+            (Column::Number, cli.number),
+            (Column::Inode, cli.inode),
+            (Column::Blocks, cli.blocks),
+            (Column::Permissions, true),
+            (Column::Mode, cli.octal),
+            (Column::Links, cli.links),
+            (Column::Size, true),
+            (Column::Count, cli.count),
+            (Column::Owner, platform::HAS_OWNERS && !cli.owner_group),
+            (Column::Group, platform::HAS_OWNERS && !cli.owner_group),
+            (Column::OwnerGroup, platform::HAS_OWNERS && cli.owner_group),
+            (Column::Git, cli.git),
+            (Column::Name, true),
+            (Column::Type, true),
+            (Column::Mime, cli.mime),
+            (Column::Modified, true),
+        ]
+        .into_iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(column, _)| column)
+        .collect()
+    } else {
+        cli.columns.clone()
+    };
+
+    // Outside a repository there are no statuses to show
+    if files.iter().all(|file| file.git_status.is_none()) {
+        columns.retain(|column| *column != Column::Git);
+    }
+    columns
+}
+
+fn print_table(files: &[FileEntry], cli: &Cli) {
+    println!("{}", render_table(files, cli));
+}
+
+/// Narrowest `--wrap` squeezes names to, however small the terminal
+const MIN_NAME_WIDTH: usize = 10;
+
+fn render_table(files: &[FileEntry], cli: &Cli) -> String {
+    let columns = table_columns(files, cli);
+    let mut builder = Builder::default();
+    builder.push_record(columns.iter().map(|column| column.header(cli)));
+    for file in files {
+        builder.push_record(columns.iter().map(|column| {
+            let mut cell = column.cell(file, cli);
+            // Units differ in width, padding them to the widest one lines up
+            // the numbers once the column is right aligned
+            if *column == Column::Size && has_length(file, cli) {
+                if let Some((_, unit)) = human_size(file.size, cli.size_units()).rsplit_once(' ') {
+                    cell.push_str(&" ".repeat(3usize.saturating_sub(unit.len())));
+                }
+            }
+            cell
+        }));
+    }
+
+    let mut table = builder.build();
+    let mut theme = match cli.style {
+        TableStyle::Rounded => TableTheme::from_style(Style::rounded()),
+        TableStyle::Sharp => TableTheme::from_style(Style::sharp()),
+        TableStyle::Modern => TableTheme::from_style(Style::modern()),
+        TableStyle::Extended => TableTheme::from_style(Style::extended()),
+        TableStyle::Ascii => TableTheme::from_style(Style::ascii()),
+        TableStyle::Psql => TableTheme::from_style(Style::psql()),
+        TableStyle::Dots => TableTheme::from_style(Style::dots()),
+        TableStyle::Blank => TableTheme::from_style(Style::blank()),
+        TableStyle::Empty => TableTheme::from_style(Style::empty()),
+    };
+    // Presets that only separate the header would otherwise draw their line
+    // under the first entry
+    if cli.no_header && theme.get_borders_mut().horizontal.is_none() {
+        theme.remove_horizontal_lines();
+    }
+    table.with(theme);
+    table.modify(ByColumnName::new("#"), Alignment::right());
+    table.modify(ByColumnName::new("Size"), Alignment::right());
+    table.modify(ByColumnName::new("Blocks"), Alignment::right());
+    table.modify(ByColumnName::new("Links"), Alignment::right());
+    table.modify(ByColumnName::new("Count"), Alignment::right());
+    if cli.use_color() {
+        table.modify(ByColumnName::new("Owner"), Color::FG_BRIGHT_YELLOW);
+    }
+    if cli.wrap {
+        // Only the names give way, the other columns are narrow already
+        let excess = table.total_width().saturating_sub(terminal_width());
+        if excess > 0 {
+            let name_width = files
+                .iter()
+                .map(|file| get_line_width(&file.name))
+                .max()
+                .unwrap_or(0);
+            let wrapped = name_width.saturating_sub(excess).max(MIN_NAME_WIDTH);
+            table.modify(ByColumnName::new("Name"), CellWidth::wrap(wrapped));
+        }
+    }
+    // Columns are styled by their header name, so the header is only
+    // removed once everything else is in place
+    if cli.no_header {
+        table.with(Remove::row(Rows::first()));
+    } else {
+        if cli.use_color() {
+            table.modify(Rows::first(), Color::FG_BRIGHT_GREEN);
+        }
+        table.modify(Rows::first(), Alignment::center());
+    }
+    table.to_string()
+}
+
+fn print_markdown(files: &[FileEntry], cli: &Cli) {
+    let columns = table_columns(files, cli);
+    let row = |cells: Vec<String>| format!("| {} |", cells.join(" | "));
+    println!(
+        "{}",
+        row(columns
+            .iter()
+            .map(|column| column.header(cli).to_string())
+            .collect())
+    );
+    println!(
+        "{}",
+        row(columns.iter().map(|_| "---".to_string()).collect())
+    );
+    for file in files {
+        println!(
+            "{}",
+            row(columns
+                .iter()
+                .map(|column| column.cell(file, cli).replace('|', "\\|"))
+                .collect())
+        );
+    }
+}
 
-#[derive(Debug, Display)]
-enum FileType {
-    File,
-    Dir,
+fn list_recursive(listings: &[Listing], cli: &Cli) {
+    // JSON and CSV go through `document_entries` instead, so every
+    // directory gets its header here
+    for (i, listing) in listings.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!(
+            "{}",
+            paint(
+                format!("{}:", listing.path.display()),
+                TextStyle::new().bold(),
+                cli.use_color()
+            )
+        );
+        print_entries(&listing.files, cli);
+        // Only this directory, the whole walk is in the `--total` footer
+        if !cli.oneline {
+            let mut totals = Totals::default();
+            totals.add(shown_entries(&listing.files, cli));
+            println!("{}", format_totals(&totals, cli));
+        }
+    }
 }
 
-#[derive(Debug, Tabled)]
-struct FileEntry {
-    #[tabled{rename="Permissions"}]
-    permissions: String,
-    #[tabled{rename="Size"}]
-    length: String,
-    #[tabled{rename="Owner"}]
-    owner: String,
-    #[tabled{rename="Name"}]
-    name: String,
-    #[tabled{rename="Type"}]
-    e_type: FileType,
-    #[tabled{rename="Modified"}]
-    modified: String,
+/// Draws the directories of a walk below `path`, keyed by their paths
+fn print_tree(path: &Path, cli: &Cli, prefix: &str, walked: &HashMap<&Path, &[FileEntry]>) {
+    let Some(files) = walked.get(path) else {
+        return;
+    };
+    let files: Vec<&FileEntry> = files
+        .iter()
+        .filter(|file| !file.is_self_or_parent())
+        .collect();
+
+    for (i, file) in files.iter().enumerate() {
+        let is_last = i == files.len() - 1;
+        let connector = if is_last { "└── " } else { "├── " };
+        if cli.long {
+            println!(
+                "{prefix}{connector}{} {} {}",
+                file.permissions,
+                format_length(file, cli),
+                file.name
+            );
+        } else {
+            println!("{prefix}{connector}{}", file.name);
+        }
+
+        // Directories past `--depth`, or already walked under another
+        // path, weren't read
+        if file.e_type == FileType::Dir && walked.contains_key(file.path.as_path()) {
+            let child_prefix = format!("{prefix}{}", if is_last { "    " } else { "│   " });
+            print_tree(&file.path, cli, &child_prefix, walked);
+        }
+    }
 }
 
-#[derive(Debug, Parser)]
-#[command(
-    version,
-    about,
-    long_about = "ls -la command in a table like format in mimicing the nu ls"
-)]
-struct Cli {
-    path: Option<PathBuf>,
+fn get_files(path: &Path, cli: &Cli) -> std::io::Result<Vec<FileEntry>> {
+    let mut candidates: Vec<(PathBuf, OsString)> = Vec::default();
+    let wanted = |file_name: &OsStr| {
+        matches_globs(file_name, &cli.glob) && !is_ignored(file_name, &cli.ignore)
+    };
+    if cli.all {
+        // The same patterns can leave out `.` and `..`, like `.*` does
+        for (entry_path, file_name) in [(path.to_path_buf(), "."), (path.join(".."), "..")] {
+            if wanted(OsStr::new(file_name)) {
+                candidates.push((entry_path, OsString::from(file_name)));
+            }
+        }
+    }
+    let not_ignored = cli.gitignore.then(|| not_ignored_paths(path));
+    match fs::read_dir(path) {
+        Ok(read_dir) => {
+            for entry in read_dir {
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(err) => {
+                        report_error(path, err, cli)?;
+                        continue;
+                    }
+                };
+                if !cli.all && !cli.almost_all && is_hidden(&entry.file_name()) {
+                    continue;
+                }
+                if !wanted(&entry.file_name()) {
+                    continue;
+                }
+                if not_ignored
+                    .as_ref()
+                    .is_some_and(|paths| !paths.contains(&entry.path()))
+                {
+                    continue;
+                }
+                candidates.push((entry.path(), entry.file_name()));
+            }
+        }
+        Err(err) => report_error(path, err, cli)?,
+    }
+    // Stat calls dominate on slow filesystems, so they're spread over
+    // threads; the order is fixed by the sort below either way
+    let mut data: Vec<FileEntry> = candidates
+        .par_iter()
+        .map(|(path, file_name)| read_entry(path, file_name, cli))
+        .collect::<std::io::Result<_>>()?;
+
+    if cli.git {
+        add_git_statuses(&mut data, path);
+    }
+
+    if cli.dirs_only {
+        data.retain(|entry| entry.e_type == FileType::Dir);
+    } else if cli.files_only {
+        data.retain(|entry| entry.e_type != FileType::Dir);
+    }
+    if cli.min_size.is_some() || cli.max_size.is_some() {
+        data.retain(|entry| {
+            (entry.e_type == FileType::Dir && !cli.du)
+                || (cli.min_size.is_none_or(|min| entry.size >= min)
+                    && cli.max_size.is_none_or(|max| entry.size <= max))
+        });
+    }
+    if cli.newer_than.is_some() || cli.older_than.is_some() {
+        let now = SystemTime::now();
+        data.retain(|entry| {
+            // Entries without the timestamp can't be placed in the window
+            let Some(age) = entry
+                .modified_at
+                .map(|time| now.duration_since(time).unwrap_or_default())
+            else {
+                return false;
+            };
+            cli.newer_than.is_none_or(|newer| age <= newer)
+                && cli.older_than.is_none_or(|older| age > older)
+        });
+    }
+    if let Some(perm) = cli.perm {
+        data.retain(|entry| !entry.unreadable && perm.matches(entry.mode));
+    }
+    if let Some(uid) = cli.owner {
+        data.retain(|entry| !entry.unreadable && entry.uid == uid);
+    }
+    if !cli.ext.is_empty() {
+        data.retain(|entry| entry.e_type == FileType::Dir || has_extension(entry, &cli.ext));
+    }
+
+    order_entries(&mut data, cli);
+    number_entries(&mut data);
+
+    Ok(data)
 }
 
-fn main() {
-    let cli = Cli::parse();
+fn number_entries(data: &mut [FileEntry]) {
+    for (i, entry) in data.iter_mut().enumerate() {
+        entry.number = i + 1;
+    }
+}
 
-    let path = cli.path.unwrap_or(PathBuf::from("."));
+fn get_file(path: &Path, cli: &Cli) -> std::io::Result<Vec<FileEntry>> {
+    let mut data = vec![read_entry(path, path.as_os_str(), cli)?];
+    if cli.git {
+        let parent = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        add_git_statuses(&mut data, parent);
+    }
+    number_entries(&mut data);
+    Ok(data)
+}
 
-    if let Ok(path_exists) = fs::exists(&path) {
-        if path_exists {
-            let files = get_files(&path);
-            let mut table = Table::new(&files);
-            table.with(Style::rounded());
-            table.modify(Columns::last(), Color::FG_BLUE);
-            table.modify(Columns::one(2), Color::FG_BRIGHT_YELLOW);
-            table.modify(Rows::first(), Color::FG_BRIGHT_GREEN);
-            table.modify(Rows::first(), Alignment::center());
-            println!("{}", table);
+fn add_git_statuses(data: &mut [FileEntry], path: &Path) {
+    // Outside a repository the statuses stay `None` and the column is dropped
+    let Some(workdir) = git::workdir(path) else {
+        return;
+    };
+    let mut cache = GIT_STATUSES.lock().unwrap_or_else(PoisonError::into_inner);
+    let statuses = cache
+        .entry(workdir)
+        .or_insert_with_key(|workdir| GitStatuses::load(workdir));
+    if let Some(statuses) = statuses {
+        for entry in data {
+            entry.git_status = Some(statuses.status_of(&entry.path));
+        }
+    }
+}
+
+fn order_entries(data: &mut [FileEntry], cli: &Cli) {
+    sort_entries(data, cli);
+    if cli.reverse {
+        data.reverse();
+    }
+    // Stable, so every section keeps the order chosen above. Directories
+    // go first within their section rather than the sections repeating
+    if cli.group_by.is_some() || cli.group_directories_first {
+        data.sort_by_key(|entry| {
+            (
+                cli.group_by.map(|group_by| group_by.section(entry)),
+                cli.group_directories_first && entry.e_type != FileType::Dir,
+            )
+        });
+    }
+}
+
+fn sort_entries(data: &mut [FileEntry], cli: &Cli) {
+    let ignore_case = cli.ignore_case;
+    // `sort_by` is stable, so entries comparing equal keep directory order
+    match cli.sort {
+        SortKey::Name => data.sort_by(|a, b| compare_names(a, b, ignore_case)),
+        SortKey::Size => data.sort_by_key(|e| e.size),
+        SortKey::Time => data.sort_by_key(|e| e.shown_at),
+        SortKey::Type => data.sort_by(|a, b| {
+            a.e_type
+                .cmp(&b.e_type)
+                .then(compare_names(a, b, ignore_case))
+        }),
+        SortKey::Version => data.sort_by(|a, b| {
+            if ignore_case {
+                compare_versions(
+                    &a.file_name.to_ascii_lowercase(),
+                    &b.file_name.to_ascii_lowercase(),
+                )
+            } else {
+                compare_versions(&a.file_name, &b.file_name)
+            }
+        }),
+        SortKey::Extension => data.sort_by(|a, b| {
+            a.path
+                .extension()
+                .cmp(&b.path.extension())
+                .then(compare_names(a, b, ignore_case))
+        }),
+    }
+}
+
+/// Compares names run by run, with runs of digits compared by their
+/// numeric value like `ls -v`
+fn compare_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    let mut a_runs = name_runs(a);
+    let mut b_runs = name_runs(b);
+    loop {
+        let ordering = match (a_runs.next(), b_runs.next()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return std::cmp::Ordering::Less,
+            (Some(_), None) => return std::cmp::Ordering::Greater,
+            (Some(a_run), Some(b_run)) => {
+                let a_is_digits = a_run.starts_with(|c: char| c.is_ascii_digit());
+                let b_is_digits = b_run.starts_with(|c: char| c.is_ascii_digit());
+                if a_is_digits && b_is_digits {
+                    // Leading zeros don't change the value, after that the
+                    // longer run is the bigger number
+                    let a_num = a_run.trim_start_matches('0');
+                    let b_num = b_run.trim_start_matches('0');
+                    a_num.len().cmp(&b_num.len()).then(a_num.cmp(b_num))
+                } else {
+                    a_run.cmp(b_run)
+                }
+            }
+        };
+        if ordering.is_ne() {
+            return ordering;
+        }
+    }
+}
+
+/// Splits a name into alternating runs of digits and non-digits
+fn name_runs(name: &str) -> impl Iterator<Item = &str> {
+    let mut rest = name;
+    std::iter::from_fn(move || {
+        let first = rest.chars().next()?;
+        let end = rest
+            .find(|c: char| c.is_ascii_digit() != first.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let (run, tail) = rest.split_at(end);
+        rest = tail;
+        Some(run)
+    })
+}
+
+/// Byte-wise comparison like `LC_COLLATE=C`, so uppercase sorts before
+/// lowercase. Locale aware collation isn't supported, `ignore_case` only
+/// folds ASCII letters.
+fn compare_names(a: &FileEntry, b: &FileEntry, ignore_case: bool) -> std::cmp::Ordering {
+    if ignore_case {
+        a.file_name
+            .to_ascii_lowercase()
+            .cmp(&b.file_name.to_ascii_lowercase())
+    } else {
+        a.file_name.cmp(&b.file_name)
+    }
+}
+
+fn is_hidden(file_name: &OsStr) -> bool {
+    file_name.as_encoded_bytes().starts_with(b".")
+}
+
+/// Immediate children of `path` that aren't excluded by the `.gitignore`
+/// files above it, `.git/info/exclude` or the global excludes file
+fn not_ignored_paths(path: &Path) -> HashSet<PathBuf> {
+    WalkBuilder::new(path)
+        .max_depth(Some(1))
+        .hidden(false)
+        .ignore(false)
+        .require_git(false)
+        .build()
+        .flatten()
+        .map(|entry| entry.into_path())
+        .collect()
+}
+
+fn matches_globs(file_name: &OsStr, patterns: &[Pattern]) -> bool {
+    patterns.is_empty()
+        || patterns
+            .iter()
+            .any(|pattern| pattern.matches(&file_name.to_string_lossy()))
+}
+
+/// Reads glob patterns the way `.gitignore` lists them, skipping blank
+/// lines and `#` comments
+fn read_ignore_file(path: &Path) -> Result<Vec<Pattern>, String> {
+    let contents = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        // Patterns only ever see names, so a trailing slash marking a
+        // directory would never match
+        .map(|line| line.trim_end_matches('/'))
+        .map(|line| Pattern::new(line).map_err(|err| format!("'{}': {}", line, err)))
+        .collect()
+}
+
+fn is_ignored(file_name: &OsStr, patterns: &[Pattern]) -> bool {
+    patterns
+        .iter()
+        .any(|pattern| pattern.matches(&file_name.to_string_lossy()))
+}
+
+fn has_extension(entry: &FileEntry, extensions: &[String]) -> bool {
+    entry.path.extension().is_some_and(|ext| {
+        extensions.iter().any(|wanted| {
+            ext.to_string_lossy()
+                .eq_ignore_ascii_case(wanted.trim_start_matches('.'))
+        })
+    })
+}
+
+fn read_entry(path: &Path, file_name: &OsStr, cli: &Cli) -> std::io::Result<FileEntry> {
+    // A file that can't be stat-ed is still listed so it doesn't silently
+    // vanish from the output
+    match get_entry(path, file_name, cli) {
+        Ok(entry) => Ok(entry),
+        Err(err) => {
+            report_error(path, err, cli)?;
+            Ok(FileEntry::unreadable(path, file_name, cli))
+        }
+    }
+}
+
+/// Counts an entry that couldn't be read, or hands the error back up to
+/// `main` under `--strict` so the listing ends there
+fn report_error(path: &Path, err: std::io::Error, cli: &Cli) -> std::io::Result<()> {
+    if cli.strict {
+        return Err(std::io::Error::new(
+            err.kind(),
+            format!("{}: {}", path.display(), err),
+        ));
+    }
+    ERRORS.fetch_add(1, Ordering::Relaxed);
+    Ok(())
+}
+
+fn get_entry(path: &Path, file_name: &OsStr, cli: &Cli) -> std::io::Result<FileEntry> {
+    let color = cli.use_color();
+    // A broken link has nothing to describe under `-L`, so it fails like
+    // any other unreadable entry
+    let meta_data = if cli.dereference {
+        fs::metadata(path)?
+    } else {
+        fs::symlink_metadata(path)?
+    };
+    let stat = platform::stat(&meta_data);
+    let e_type = platform::file_type(meta_data.file_type());
+    let is_symlink = e_type == FileType::Symlink;
+    let shown_at = cli.time.of(&meta_data);
+    let ls_style = LS_COLORS
+        .as_ref()
+        .filter(|_| color && !cli.no_lscolors)
+        .and_then(|colors| colors.style_for_path_with_metadata(path, Some(&meta_data)));
+    // `--classify` already marks directories with the same slash
+    let slash = cli.slash && !cli.classify;
+    // Joined rather than canonicalized so symlinks keep their own name
+    let full_path = cli
+        .full_path
+        .then(|| std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf()));
+    let shown_name = full_path.as_deref().map_or(file_name, Path::as_os_str);
+    let quoted = display_name(shown_name, cli);
+    let mut name = if let Some(style) = ls_style {
+        let name = quoted.as_str();
+        if slash && e_type == FileType::Dir {
+            paint(format!("{}/", name), style.to_owo_colors_style(), color)
+        } else {
+            paint(name, style.to_owo_colors_style(), color)
+        }
+    } else if is_symlink {
+        parse_symlink_name(path, &quoted, color)
+    } else {
+        parse_file_name(&quoted, e_type == FileType::Dir, slash, color)
+    };
+    if cli.hyperlink == ColorWhen::Always {
+        name = hyperlink(&name, path);
+    }
+    if cli.icons {
+        name = format!("{} {}", icon_for(path, e_type), name);
+    }
+    if cli.classify {
+        name.push_str(classify_suffix(stat.mode, e_type));
+    }
+    if cli.mark_empty
+        && e_type == FileType::Dir
+        && file_name != "."
+        && file_name != ".."
+        && fs::read_dir(path).is_ok_and(|mut read_dir| read_dir.next().is_none())
+    {
+        name.push_str(&paint(" (empty)", TextStyle::new().bright_black(), color));
+    }
+    // A bare list of names is meant for other programs, which would
+    // choke on the target, and a grid shows just the names like `ls`
+    if is_symlink && !cli.oneline && !cli.grid {
+        name = format!("{} -> {}", name, parse_symlink_target(path, color));
+    }
+    #[cfg(unix)]
+    let mut permissions = permissions_to_string(e_type, stat.mode, color);
+    #[cfg(windows)]
+    let mut permissions = paint(
+        platform::attributes(&meta_data),
+        THEME.permission_read,
+        color,
+    );
+    if cli.xattr && platform::has_xattrs(path) {
+        permissions.push_str(&paint("@", TextStyle::new().bright_cyan(), color));
+    }
+    Ok(FileEntry {
+        permissions,
+        owner: if cli.numeric {
+            stat.uid.to_string()
+        } else {
+            uid_to_string(stat.uid)
+        },
+        uid: stat.uid,
+        group: if cli.numeric {
+            stat.gid.to_string()
+        } else {
+            gid_to_string(stat.gid)
+        },
+        name,
+        e_type,
+        // Not every filesystem records every timestamp, so a missing one
+        // is left blank
+        modified: if let Some(modi) = shown_at {
+            paint(format_time(modi, cli), age_style(modi), color)
+        } else {
+            String::default()
+        },
+        path: path.to_path_buf(),
+        file_name: file_name.to_string_lossy().into_owned(),
+        size: if meta_data.is_file() {
+            meta_data.len()
+        } else if cli.du && e_type == FileType::Dir && file_name != "." && file_name != ".." {
+            dir_size(path)
         } else {
-            println!("{}", "Path does not exist".red());
+            0
+        },
+        modified_at: meta_data.modified().ok(),
+        shown_at,
+        mode: stat.mode,
+        inode: stat.inode,
+        links: stat.links,
+        blocks: stat.blocks,
+        rdev: stat.rdev,
+        // Asking for either column is enough, `--count` and `--mime` only
+        // add them to the default ones
+        child_count: if (cli.count || cli.columns.contains(&Column::Count))
+            && e_type == FileType::Dir
+        {
+            fs::read_dir(path).ok().map(|read_dir| read_dir.count())
+        } else {
+            None
+        },
+        mime: if (cli.mime || cli.columns.contains(&Column::Mime)) && meta_data.is_file() {
+            Some(sniff_mime(path))
+        } else {
+            None
+        },
+        git_status: None,
+        number: 0,
+        unreadable: false,
+    })
+}
+
+/// Guesses the MIME type from magic bytes, which only needs the start of
+/// the file
+fn sniff_mime(path: &Path) -> &'static str {
+    match infer::get_from_path(path) {
+        Ok(Some(kind)) => kind.mime_type(),
+        _ => "application/octet-stream",
+    }
+}
+
+fn paint(text: impl Display, style: TextStyle, color: bool) -> String {
+    if color {
+        text.style(style).to_string()
+    } else {
+        text.to_string()
+    }
+}
+
+/// `message` as printed on stderr, red unless stderr goes uncolored
+fn error_text(message: impl Display) -> String {
+    paint(
+        message,
+        TextStyle::new().red(),
+        STDERR_COLOR.load(Ordering::Relaxed),
+    )
+}
+
+fn parse_owner(owner: &str) -> Result<u32, String> {
+    if let Ok(uid) = owner.parse() {
+        return Ok(uid);
+    }
+    platform::uid_by_name(owner).ok_or_else(|| format!("no such user '{}'", owner))
+}
+
+/// Parses durations like `90s`, `2d` or `1h30m`
+fn parse_duration(duration: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid duration '{}'", duration);
+    if duration.is_empty() {
+        return Err(invalid());
+    }
+    let mut seconds: u64 = 0;
+    let mut rest = duration;
+    while !rest.is_empty() {
+        let split = rest
+            .find(|ch: char| !ch.is_ascii_digit())
+            .ok_or_else(invalid)?;
+        let amount: u64 = rest[..split].parse().map_err(|_| invalid())?;
+        let unit = match rest[split..].chars().next() {
+            Some('s') => 1,
+            Some('m') => 60,
+            Some('h') => 3600,
+            Some('d') => 86400,
+            Some('w') => 604800,
+            _ => return Err(invalid()),
+        };
+        seconds = amount
+            .checked_mul(unit)
+            .and_then(|amount| seconds.checked_add(amount))
+            .ok_or_else(invalid)?;
+        rest = &rest[split + 1..];
+    }
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Parses sizes in the units `human_size` prints: `K`, `M`, `G` and `T`
+/// with an optional `iB` are powers of 1024, with just `B` powers of 1000
+fn parse_size(size: &str) -> Result<u64, String> {
+    let invalid = || format!("invalid size '{}'", size);
+    let split = size
+        .find(|ch: char| !ch.is_ascii_digit() && ch != '.')
+        .unwrap_or(size.len());
+    let (number, suffix) = size.split_at(split);
+    let number: f64 = number.parse().map_err(|_| invalid())?;
+
+    let suffix = suffix.to_ascii_lowercase();
+    let (prefix, base) = if let Some(prefix) = suffix.strip_suffix("ib") {
+        (prefix, 1024.0)
+    } else if let Some(prefix) = suffix.strip_suffix('b').filter(|prefix| !prefix.is_empty()) {
+        (prefix, 1000.0)
+    } else {
+        (suffix.as_str(), 1024.0)
+    };
+    let exponent = match prefix {
+        "" | "b" => 0,
+        "k" => 1,
+        "m" => 2,
+        "g" => 3,
+        "t" => 4,
+        _ => return Err(invalid()),
+    };
+    Ok((number * f64::powi(base, exponent)) as u64)
+}
+
+fn parse_perm(spec: &str) -> Result<PermFilter, String> {
+    match spec {
+        "world-writable" => return Ok(PermFilter::All(0o002)),
+        "setuid" => return Ok(PermFilter::All(0o4000)),
+        "setgid" => return Ok(PermFilter::All(0o2000)),
+        "sticky" => return Ok(PermFilter::All(0o1000)),
+        _ => {}
+    }
+
+    let invalid = || format!("invalid permission spec '{}'", spec);
+    let (prefix, mode) = match spec.strip_prefix(['-', '/']) {
+        Some(mode) => (spec.chars().next(), mode),
+        None => (None, spec),
+    };
+
+    let bits = if !mode.is_empty() && mode.chars().all(|ch| ch.is_digit(8)) {
+        u32::from_str_radix(mode, 8)
+            .ok()
+            .filter(|bits| *bits <= 0o7777)
+            .ok_or_else(invalid)?
+    } else {
+        // Symbolic clauses like `u+x,o+w`, where no users means all of them
+        let mut bits = 0;
+        for clause in mode.split(',') {
+            let (who, perms) = clause.split_once('+').ok_or_else(invalid)?;
+            let who = if who.is_empty() { "a" } else { who };
+            for user in who.chars() {
+                let sets: &[(u32, u32)] = match user {
+                    'u' => &[(6, 0o4000)],
+                    'g' => &[(3, 0o2000)],
+                    'o' => &[(0, 0)],
+                    'a' => &[(6, 0o4000), (3, 0o2000), (0, 0)],
+                    _ => return Err(invalid()),
+                };
+                for (shift, special) in sets {
+                    bits |= perms_bits(perms, *shift, *special).ok_or_else(invalid)?;
+                }
+            }
+        }
+        // An exact symbolic mode reads oddly, so they always mean at least
+        // those bits
+        if prefix.is_none() {
+            return Ok(PermFilter::All(bits));
+        }
+        bits
+    };
+
+    Ok(match prefix {
+        Some('-') => PermFilter::All(bits),
+        Some(_) => PermFilter::Any(bits),
+        None => PermFilter::Exact(bits),
+    })
+}
+
+/// Bits of `rwxst` letters for the set of users `shift` places up
+fn perms_bits(perms: &str, shift: u32, special: u32) -> Option<u32> {
+    perms.chars().try_fold(0, |bits, perm| {
+        let bit = match perm {
+            'r' => 0o4 << shift,
+            'w' => 0o2 << shift,
+            'x' => 0o1 << shift,
+            's' => special,
+            't' => 0o1000,
+            _ => return None,
+        };
+        Some(bits | bit)
+    })
+}
+
+fn parse_time_format(fmt: &str) -> Result<String, String> {
+    // chrono only reports bad specifiers while formatting, so check the
+    // parsed items up front instead of failing halfway through the listing
+    if StrftimeItems::new(fmt).any(|item| item == Item::Error) {
+        Err(format!("invalid strftime format '{}'", fmt))
+    } else {
+        Ok(fmt.to_string())
+    }
+}
+
+fn format_time(time: SystemTime, cli: &Cli) -> String {
+    let fmt = cli.time_format.as_deref().or(cli.time_style.format());
+    let Some(fmt) = fmt else {
+        return time_ago(time.into(), Utc::now());
+    };
+    if cli.utc {
+        format!("{}", DateTime::<Utc>::from(time).format(fmt))
+    } else {
+        format!("{}", DateTime::<Local>::from(time).format(fmt))
+    }
+}
+
+/// Tints timestamps by how recent they are, brightest for the last hour
+fn age_style(time: SystemTime) -> TextStyle {
+    let age = SystemTime::now()
+        .duration_since(time)
+        .unwrap_or_default()
+        .as_secs();
+    match age {
+        0..3600 => THEME.age_hour,
+        3600..86400 => THEME.age_day,
+        86400..2592000 => THEME.age_month,
+        _ => THEME.age_old,
+    }
+}
+
+fn time_ago(date: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let seconds = (now - date).num_seconds();
+    if seconds < 0 {
+        return "in the future".to_string();
+    }
+
+    let (amount, unit) = match seconds {
+        0..60 => (seconds, "second"),
+        60..3600 => (seconds / 60, "minute"),
+        3600..86400 => (seconds / 3600, "hour"),
+        86400..604800 => (seconds / 86400, "day"),
+        604800..2592000 => (seconds / 604800, "week"),
+        2592000..31536000 => (seconds / 2592000, "month"),
+        _ => (seconds / 31536000, "year"),
+    };
+    if amount == 1 {
+        format!("{} {} ago", amount, unit)
+    } else {
+        format!("{} {}s ago", amount, unit)
+    }
+}
+
+/// `name` shortened to `--max-name-width` and then quoted, so the quotes
+/// are never cut off
+fn display_name(name: &OsStr, cli: &Cli) -> String {
+    match cli.max_name_width {
+        Some(width) => {
+            let truncated = truncate_name(&name.to_string_lossy(), width);
+            quote_name(OsStr::new(&truncated), cli.quoting_style)
         }
+        None => quote_name(name, cli.quoting_style),
+    }
+}
+
+/// Shortens `name` to `width` characters, the last one being the ellipsis
+fn truncate_name(name: &str, width: u64) -> String {
+    let width = width as usize;
+    if name.chars().count() <= width {
+        return name.to_string();
+    }
+    let mut truncated: String = name.chars().take(width - 1).collect();
+    truncated.push('…');
+    truncated
+}
+
+fn parse_file_name(name: &str, is_dir: bool, slash: bool, color: bool) -> String {
+    if is_dir && slash {
+        paint(format!("{}/", name), THEME.dir, color)
+    } else if is_dir {
+        paint(name, THEME.dir, color)
     } else {
-        println!("{}", "Error reading directory".red());
+        paint(name, THEME.file, color)
+    }
+}
+
+fn parse_symlink_name(path: &Path, name: &str, color: bool) -> String {
+    // `symlink_metadata` succeeded for the link itself, so failing to follow
+    // it here means the target is gone
+    match fs::metadata(path) {
+        Ok(target) => parse_file_name(name, target.is_dir(), false, color),
+        Err(_) => paint(name, TextStyle::new().red(), color),
     }
 }
 
-fn get_files(path: &Path) -> Vec<FileEntry> {
-    let mut data: Vec<FileEntry> = Vec::default();
-    if let Ok(read_dir) = fs::read_dir(path) {
-        for entry in read_dir.flatten() {
-            get_entries(entry, &mut data);
+/// Wraps `text` in an OSC 8 escape linking to `path`
+fn hyperlink(text: &str, path: &Path) -> String {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut uri = String::from("file://");
+    for byte in path.as_os_str().as_encoded_bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-._~".contains(byte) {
+            uri.push(*byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
         }
     }
+    format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", uri, text)
+}
 
-    data
+/// Whether the terminal is one known to understand OSC 8 hyperlinks, others
+/// may print the escape codes as garbage
+fn supports_hyperlinks() -> bool {
+    let env = |name| std::env::var_os(name).is_some();
+    let term_program = std::env::var("TERM_PROGRAM").unwrap_or_default();
+    std::io::stdout().is_terminal()
+        && (env("VTE_VERSION")
+            || env("KITTY_WINDOW_ID")
+            || env("WT_SESSION")
+            || env("KONSOLE_VERSION")
+            || ["iTerm.app", "WezTerm", "vscode", "ghostty"].contains(&term_program.as_str()))
 }
 
-fn get_entries(entry: fs::DirEntry, data: &mut Vec<FileEntry>) {
-    if let Ok(meta_data) = fs::metadata(entry.path()) {
-        data.push(FileEntry {
-            permissions: permissions_to_string(&meta_data, meta_data.permissions().mode()),
-            length: if meta_data.is_file() {
-                parse_file_size(meta_data.len())
-            } else {
-                "-".cyan().to_string()
-            },
-            owner: uid_to_string(meta_data.uid()),
-            name: parse_file_name(entry),
-            e_type: if meta_data.is_dir() {
-                FileType::Dir
-            } else {
-                FileType::File
-            },
-            modified: if let Ok(modi) = meta_data.modified() {
-                let date: DateTime<Utc> = modi.into();
-                format!("{}", date.format("%e %b %H:%M"))
-            } else {
-                String::default()
-            },
-        });
+fn quote_name(name: &OsStr, style: QuotingStyle) -> String {
+    let name = name.to_string_lossy();
+    match style {
+        QuotingStyle::Auto | QuotingStyle::Literal => name.into_owned(),
+        QuotingStyle::Shell => {
+            // C1 controls like the 8-bit CSI are as dangerous on a terminal
+            // as the ASCII ones, so only printable non-ASCII passes
+            let plain = name.chars().all(|ch| {
+                (!ch.is_ascii() && !ch.is_control())
+                    || ch.is_ascii_alphanumeric()
+                    || "_-./:,+@%=^".contains(ch)
+            });
+            if plain {
+                return name.into_owned();
+            }
+            let mut quoted = String::from("'");
+            for ch in name.chars() {
+                match ch {
+                    '\'' => quoted.push_str("'\\''"),
+                    // Control characters can't be written inside single
+                    // quotes, so they go in an ANSI-C quoted `$'...'` part
+                    ch if ch.is_control() => {
+                        quoted.push_str("'$'");
+                        quoted.push_str(&escape_char(ch));
+                        quoted.push_str("''");
+                    }
+                    ch => quoted.push(ch),
+                }
+            }
+            quoted.push('\'');
+            quoted
+        }
+        QuotingStyle::Escape => name
+            .chars()
+            .map(|ch| match ch {
+                '\\' => "\\\\".to_string(),
+                ' ' => "\\ ".to_string(),
+                ch if ch.is_control() => escape_char(ch),
+                ch => ch.to_string(),
+            })
+            .collect(),
+    }
+}
+
+fn escape_char(ch: char) -> String {
+    match ch {
+        '\n' => "\\n".to_string(),
+        '\t' => "\\t".to_string(),
+        '\r' => "\\r".to_string(),
+        // Every byte of the UTF-8 encoding, which is what `$'...'` reads
+        ch => ch
+            .encode_utf8(&mut [0; 4])
+            .bytes()
+            .map(|byte| format!("\\{:03o}", byte))
+            .collect(),
     }
 }
 
-fn parse_file_name(entry: fs::DirEntry) -> String {
-    if entry.metadata().unwrap().is_dir() {
-        (entry
-            .file_name()
-            .into_string()
-            .unwrap_or("Unknown name".into()))
-        .blue()
-        .bold()
-        .to_string()
+fn parse_symlink_target(path: &Path, color: bool) -> String {
+    if fs::metadata(path).is_err() {
+        paint("[missing]", TextStyle::new().red(), color)
     } else {
-        (entry
-            .file_name()
-            .into_string()
-            .unwrap_or("Unknown name".into()))
-        .white()
-        .to_string()
+        let target = fs::read_link(path).unwrap_or_default();
+        paint(target.display(), TextStyle::new().cyan(), color)
+    }
+}
+
+fn classify_suffix(mode: u32, file_type: FileType) -> &'static str {
+    match file_type {
+        FileType::Dir => "/",
+        FileType::Symlink => "@",
+        FileType::Fifo => "|",
+        FileType::Socket => "=",
+        FileType::File if mode & 0o111 != 0 => "*",
+        FileType::File | FileType::BlockDevice | FileType::CharDevice => "",
+    }
+}
+
+fn icon_for(path: &Path, file_type: FileType) -> char {
+    match file_type {
+        FileType::Dir => return '\u{f07b}',
+        FileType::Symlink => return '\u{f0c1}',
+        FileType::Fifo => return '\u{f0ec}',
+        FileType::Socket => return '\u{f1e6}',
+        FileType::BlockDevice | FileType::CharDevice => return '\u{f0a0}',
+        FileType::File => {}
     }
+
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "rs" => '\u{e7a8}',
+        "py" => '\u{e606}',
+        "js" | "mjs" | "cjs" => '\u{e74e}',
+        "ts" => '\u{e628}',
+        "go" => '\u{e627}',
+        "c" | "h" => '\u{e61e}',
+        "cpp" | "cc" | "hpp" => '\u{e61d}',
+        "java" => '\u{e738}',
+        "html" => '\u{e736}',
+        "css" => '\u{e749}',
+        "md" => '\u{f48a}',
+        "json" => '\u{e60b}',
+        "toml" | "yaml" | "yml" | "ini" => '\u{e615}',
+        "sh" | "bash" | "zsh" | "fish" => '\u{f489}',
+        "lock" => '\u{f023}',
+        "txt" => '\u{f15c}',
+        "png" | "jpg" | "jpeg" | "gif" | "svg" | "webp" => '\u{f1c5}',
+        "zip" | "tar" | "gz" | "xz" | "7z" => '\u{f410}',
+        "pdf" => '\u{f1c1}',
+        _ => '\u{f15b}',
+    }
+}
+
+fn format_git_status(status: Option<git2::Status>, color: bool) -> String {
+    let Some(status) = status else {
+        return String::new();
+    };
+    let code = git::status_code(status);
+    let style = match code {
+        "A" => TextStyle::new().green(),
+        "M" => TextStyle::new().yellow(),
+        "R" => TextStyle::new().cyan(),
+        _ => TextStyle::new().red(),
+    };
+    paint(code, style, color)
 }
 
-fn parse_file_size(size: u64) -> String {
-    if size < 1024 {
-        size.to_string().green().to_string()
-    } else if size > 1024 * 1024 {
-        format!("{}m", (size as f64 / (1024.0 * 1024.0)).round())
-            .bright_yellow()
-            .to_string()
+/// Whether `format_length` prints a size rather than device numbers or `-`
+fn has_length(entry: &FileEntry, cli: &Cli) -> bool {
+    entry.e_type == FileType::File
+        || (cli.du && entry.e_type == FileType::Dir && !entry.is_self_or_parent())
+}
+
+fn format_length(entry: &FileEntry, cli: &Cli) -> String {
+    if has_length(entry, cli) {
+        parse_file_size(entry.size, cli.size_units(), cli.use_color())
+    } else if matches!(entry.e_type, FileType::BlockDevice | FileType::CharDevice) {
+        // Devices have no size, `ls` shows which driver and unit they are
+        paint(
+            {
+                let (major, minor) = platform::device_numbers(entry.rdev);
+                format!("{}, {}", major, minor)
+            },
+            TextStyle::new().bright_yellow(),
+            cli.use_color(),
+        )
     } else {
-        format!("{}k", (size as f64 / 1024.0).round())
-            .bright_yellow()
-            .to_string()
+        paint("-", TextStyle::new().cyan(), cli.use_color())
     }
 }
 
-fn uid_to_string(uid: u32) -> String {
-    if let Ok(Some(user)) = User::from_uid(Uid::from(uid)) {
-        user.name.to_string()
+fn parse_file_size(size: u64, units: SizeUnits, color: bool) -> String {
+    let base: u64 = if units == SizeUnits::Si { 1000 } else { 1024 };
+    let thresholds = THEME.size_thresholds.unwrap_or(SizeThresholds {
+        medium: base.pow(2),
+        large: base.pow(3),
+    });
+    let style = if size < thresholds.medium {
+        THEME.size_small
+    } else if size < thresholds.large {
+        THEME.size_medium
     } else {
-        "User error".to_string()
+        THEME.size_large
+    };
+    paint(human_size(size, units), style, color)
+}
+
+fn human_size(size: u64, units: SizeUnits) -> String {
+    let (base, units) = match units {
+        SizeUnits::Bytes => return size.to_string(),
+        SizeUnits::Si => (1000.0, ["B", "kB", "MB", "GB", "TB"]),
+        SizeUnits::Binary => (1024.0, ["B", "KiB", "MiB", "GiB", "TiB"]),
+    };
+
+    let mut value = size as f64;
+    let mut unit = 0;
+    while value >= base && unit < units.len() - 1 {
+        value /= base;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", size, units[0])
+    } else {
+        format!("{:.1} {}", value, units[unit])
+    }
+}
+
+fn cached_name(cache: &Mutex<HashMap<u32, String>>, id: u32, lookup: fn(u32) -> String) -> String {
+    // Lookups go through the passwd and group databases, which can be slow
+    // when they're served over the network, so each id is resolved once
+    let mut cache = cache.lock().unwrap_or_else(PoisonError::into_inner);
+    cache.entry(id).or_insert_with(|| lookup(id)).clone()
+}
+
+fn uid_to_string(uid: u32) -> String {
+    cached_name(&USER_NAMES, uid, |uid| {
+        // Minimal containers often have no passwd entry for the user
+        // running in them, the number is still better than nothing
+        platform::user_name(uid).unwrap_or_else(|| uid.to_string())
+    })
+}
+
+fn gid_to_string(gid: u32) -> String {
+    cached_name(&GROUP_NAMES, gid, |gid| {
+        platform::group_name(gid).unwrap_or_else(|| gid.to_string())
+    })
+}
+
+/// Total size of the files below `path`, without following symlinks
+fn dir_size(path: &Path) -> u64 {
+    // Recursive listings size the same directories again one level down, so
+    // every walked directory is remembered
+    if let Some(size) = DIR_SIZES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(path)
+    {
+        return *size;
     }
+
+    let entries: Vec<fs::DirEntry> = fs::read_dir(path)
+        .map(|read_dir| read_dir.flatten().collect())
+        .unwrap_or_default();
+    let size = entries
+        .par_iter()
+        .map(|entry| match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => dir_size(&entry.path()),
+            Ok(file_type) if file_type.is_file() => entry.metadata().map_or(0, |meta| meta.len()),
+            _ => 0,
+        })
+        .sum();
+    DIR_SIZES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(path.to_path_buf(), size);
+    size
+}
+
+/// Permission bits including setuid, setgid and sticky, e.g. `0755`
+fn octal_mode(mode: u32) -> String {
+    format!("{:04o}", mode & 0o7777)
 }
 
-fn permissions_to_string(meta_data: &fs::Metadata, mode: u32) -> String {
+#[cfg(unix)]
+fn permissions_to_string(file_type: FileType, mode: u32, color: bool) -> String {
     let mut result = String::new();
     let flags = [
         (0o400, 'r'),
@@ -163,30 +2813,192 @@ fn permissions_to_string(meta_data: &fs::Metadata, mode: u32) -> String {
         (0o001, 'x'),
     ];
 
-    if meta_data.is_dir() {
-        result.push_str("d".bright_blue().to_string().as_str());
-    } else {
-        result.push_str(".".white().to_string().as_str());
-    }
+    // Every character carries its own reset, so bold has to be part of each
+    // style rather than wrapped around the whole string
+    let bold = TextStyle::new().bold();
+    let indicator = match file_type {
+        FileType::Dir => paint("d", bold.bright_blue(), color),
+        FileType::File => paint(".", bold.white(), color),
+        FileType::Symlink => paint("l", bold.cyan(), color),
+        FileType::Fifo => paint("p", bold.yellow(), color),
+        FileType::Socket => paint("s", bold.magenta(), color),
+        FileType::BlockDevice => paint("b", bold.bright_yellow(), color),
+        FileType::CharDevice => paint("c", bold.bright_yellow(), color),
+    };
+    result.push_str(&indicator);
+
+    // setuid, setgid and sticky replace the execute bit of the owner, group
+    // and others sets, lowercase when execute is also set
+    let special = [(0o4000, 's', 'S'), (0o2000, 's', 'S'), (0o1000, 't', 'T')];
 
     for (i, (bit, ch)) in flags.iter().enumerate() {
-        let colored = if mode & bit != 0 {
+        let special_bit = special
+            .get(i / 3)
+            .filter(|(special_bit, _, _)| i % 3 == 2 && mode & special_bit != 0);
+        let colored = if let Some((_, with_exec, without_exec)) = special_bit {
+            let ch = if mode & bit != 0 {
+                with_exec
+            } else {
+                without_exec
+            };
+            paint(ch, THEME.permission_special, color)
+        } else if mode & bit != 0 {
             if i < 3 {
                 match ch {
-                    'x' => ch.bright_yellow().to_string(),
-                    'w' => ch.bright_red().to_string(),
-                    'r' => ch.bright_yellow().to_string(),
+                    'x' => paint(ch, THEME.permission_exec, color),
+                    'w' => paint(ch, THEME.permission_write, color),
+                    'r' => paint(ch, THEME.permission_read, color),
                     _ => ch.to_string(),
                 }
             } else {
-                ch.green().to_string()
+                paint(ch, THEME.permission_other, color)
             }
         } else {
-            "-".to_string().bright_black().to_string()
+            paint("-", THEME.permission_none, color)
         };
         result.push_str(&colored);
         // Add space after each permission set (owner, group, others)
     }
 
-    result.bold().to_string()
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_names_into_runs() {
+        assert_eq!(
+            name_runs("file10.tar.gz").collect::<Vec<_>>(),
+            ["file", "10", ".tar.gz"]
+        );
+        assert_eq!(
+            name_runs("2024-01").collect::<Vec<_>>(),
+            ["2024", "-", "01"]
+        );
+        assert_eq!(name_runs("").count(), 0);
+    }
+
+    #[test]
+    fn compares_versions() {
+        use std::cmp::Ordering::{Greater, Less};
+        assert_eq!(compare_versions("file2", "file10"), Less);
+        assert_eq!(compare_versions("v1.10.0", "v1.9.3"), Greater);
+        // Same value, the zeros only break the tie
+        assert_eq!(compare_versions("file010", "file9"), Greater);
+        assert_eq!(compare_versions("file007", "file7"), Less);
+        // Digits against anything else compare as plain text
+        assert_eq!(compare_versions("1file", "afile"), Less);
+        assert_eq!(compare_versions("10", "9a"), Greater);
+        assert_eq!(compare_versions("file", "file1"), Less);
+    }
+
+    #[test]
+    fn sorts_versions_ignoring_case() {
+        let names = |args: &[&str]| {
+            let cli = Cli::parse_from([&["nuls", "--sort", "version"], args].concat());
+            let mut entries: Vec<FileEntry> = ["b10", "B2", "a1"]
+                .into_iter()
+                .map(|name| FileEntry::unreadable(Path::new(name), OsStr::new(name), &cli))
+                .collect();
+            sort_entries(&mut entries, &cli);
+            entries
+                .into_iter()
+                .map(|entry| entry.file_name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(&[]), ["B2", "a1", "b10"]);
+        assert_eq!(names(&["--ignore-case"]), ["a1", "B2", "b10"]);
+    }
+
+    #[test]
+    fn quotes_names_for_the_shell() {
+        let quote = |name: &str| quote_name(OsStr::new(name), QuotingStyle::Shell);
+        assert_eq!(quote("plain-name.txt"), "plain-name.txt");
+        assert_eq!(quote("héllo"), "héllo");
+        assert_eq!(quote("two words"), "'two words'");
+        assert_eq!(quote("it's"), "'it'\\''s'");
+        assert_eq!(quote("a\nb"), "'a'$'\\n''b'");
+        assert_eq!(quote("esc\u{1b}[31m"), "'esc'$'\\033''[31m'");
+        assert_eq!(quote("c1\u{9b}x"), "'c1'$'\\302\\233''x'");
+    }
+
+    #[test]
+    fn escapes_names() {
+        let escape = |name: &str| quote_name(OsStr::new(name), QuotingStyle::Escape);
+        assert_eq!(escape("two words"), "two\\ words");
+        assert_eq!(escape("back\\slash"), "back\\\\slash");
+        assert_eq!(escape("c1\u{9b}x"), "c1\\302\\233x");
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("1h30m"), Ok(Duration::from_secs(5400)));
+        assert_eq!(parse_duration("2w"), Ok(Duration::from_secs(1_209_600)));
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("10").is_err());
+        assert!(parse_duration("3y").is_err());
+        assert!(parse_duration("99999999999999999w").is_err());
+        assert!(parse_duration("18446744073709551615s1s").is_err());
+    }
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size("1K"), Ok(1024));
+        assert_eq!(parse_size("1KiB"), Ok(1024));
+        assert_eq!(parse_size("1kB"), Ok(1000));
+        assert_eq!(parse_size("1.5M"), Ok(1_572_864));
+        assert_eq!(parse_size("2GB"), Ok(2_000_000_000));
+        assert!(parse_size("").is_err());
+        assert!(parse_size("10X").is_err());
+    }
+
+    #[test]
+    fn parses_sizes_in_any_case() {
+        assert_eq!(parse_size("10B"), Ok(10));
+        assert_eq!(parse_size("10b"), Ok(10));
+        assert_eq!(parse_size("1t"), Ok(1 << 40));
+        assert_eq!(parse_size("1mib"), Ok(1 << 20));
+        assert_eq!(parse_size("1MB"), Ok(1_000_000));
+        assert!(parse_size("K").is_err());
+        assert!(parse_size("1.2.3K").is_err());
+    }
+
+    #[test]
+    fn parses_perms() {
+        assert!(matches!(parse_perm("755"), Ok(PermFilter::Exact(0o755))));
+        assert!(matches!(parse_perm("-o+w"), Ok(PermFilter::All(0o002))));
+        assert!(matches!(parse_perm("/111"), Ok(PermFilter::Any(0o111))));
+        assert!(matches!(parse_perm("u+x,g+x"), Ok(PermFilter::All(0o110))));
+        assert!(matches!(parse_perm("+x"), Ok(PermFilter::All(0o111))));
+        assert!(matches!(parse_perm("setuid"), Ok(PermFilter::All(0o4000))));
+        assert!(parse_perm("17777").is_err());
+        assert!(parse_perm("z+x").is_err());
+        assert!(parse_perm("u+q").is_err());
+    }
+
+    #[test]
+    fn parses_symbolic_perms() {
+        assert!(matches!(parse_perm("/o+w"), Ok(PermFilter::Any(0o002))));
+        assert!(matches!(parse_perm("u+rwx"), Ok(PermFilter::All(0o700))));
+        assert!(matches!(parse_perm("u+s"), Ok(PermFilter::All(0o4000))));
+        assert!(matches!(parse_perm("o+t"), Ok(PermFilter::All(0o1000))));
+        assert!(matches!(parse_perm("-4000"), Ok(PermFilter::All(0o4000))));
+        assert!(parse_perm("").is_err());
+        assert!(parse_perm("u-x").is_err());
+    }
+
+    #[test]
+    fn matches_perms() {
+        assert!(PermFilter::Exact(0o755).matches(0o100755));
+        assert!(!PermFilter::Exact(0o755).matches(0o100775));
+        assert!(PermFilter::All(0o002).matches(0o40777));
+        assert!(!PermFilter::All(0o006).matches(0o40772));
+        assert!(PermFilter::Any(0o111).matches(0o100744));
+        assert!(!PermFilter::Any(0o111).matches(0o100644));
+        assert!(PermFilter::Any(0).matches(0o100644));
+    }
 }
@@ -0,0 +1,105 @@
+use nix::{
+    sys::signal::{signal, SigHandler, Signal},
+    unistd::{dup2_stdout, pipe},
+};
+use std::{
+    env,
+    fs::File,
+    io::{self, Read, Write},
+    os::fd::{AsFd, OwnedFd},
+    process::{Command, Stdio},
+    thread::{self, JoinHandle},
+};
+use tabled::grid::util::string::get_line_width;
+use terminal_size::{terminal_size_of, Height, Width};
+
+/// Collects the process' stdout while it's running, so the listing is only
+/// paged when it doesn't fit on the screen
+pub struct Pager {
+    stdout: OwnedFd,
+    output: JoinHandle<Vec<u8>>,
+    force: bool,
+}
+
+impl Pager {
+    /// Points stdout at a pipe read into memory, paging the result even
+    /// when it fits if `force` is set. Returns `None` when stdout can't be
+    /// redirected, leaving it untouched.
+    pub fn start(force: bool) -> Option<Self> {
+        let stdout = io::stdout().as_fd().try_clone_to_owned().ok()?;
+        let (read, write) = pipe().ok()?;
+        dup2_stdout(&write).ok()?;
+        // Restoring stdout in `finish` then closes the last write end
+        drop(write);
+        let output = thread::spawn(move || {
+            let mut output = Vec::new();
+            let _ = File::from(read).read_to_end(&mut output);
+            output
+        });
+        Some(Pager {
+            stdout,
+            output,
+            force,
+        })
+    }
+
+    /// Restores stdout and prints what was collected, through `$PAGER`, or
+    /// `less`, when it's taller than the terminal
+    pub fn finish(self) {
+        let _ = io::stdout().flush();
+        let _ = dup2_stdout(&self.stdout);
+        let Ok(output) = self.output.join() else {
+            return;
+        };
+        let paged = (self.force || !fits(&output, &self.stdout)) && page(&output);
+        if !paged {
+            let mut stdout = io::stdout().lock();
+            let _ = stdout.write_all(&output);
+            let _ = stdout.flush();
+        }
+    }
+}
+
+/// Whether `output` fits on the terminal `stdout` points at, counting the
+/// rows long lines wrap onto
+fn fits(output: &[u8], stdout: &OwnedFd) -> bool {
+    let Some((Width(width), Height(height))) = terminal_size_of(stdout) else {
+        return true;
+    };
+    let width = usize::from(width).max(1);
+    let rows: usize = String::from_utf8_lossy(output)
+        .lines()
+        .map(|line| get_line_width(line).div_ceil(width).max(1))
+        .sum();
+    rows < usize::from(height)
+}
+
+/// Runs `$PAGER`, or `less`, through the shell like git does and feeds it
+/// `output`. Returns `false` when the pager can't be started.
+fn page(output: &[u8]) -> bool {
+    let command = env::var("PAGER")
+        .ok()
+        .filter(|pager| !pager.trim().is_empty())
+        .unwrap_or_else(|| "less".to_string());
+    // `R` keeps the colors, unless the user configured `less` already
+    let Ok(mut child) = Command::new("sh")
+        .arg("-c")
+        .arg(&command)
+        .env("LESS", env::var_os("LESS").unwrap_or_else(|| "R".into()))
+        .stdin(Stdio::piped())
+        .spawn()
+    else {
+        return false;
+    };
+    // Quitting the pager early closes the pipe, which should only end the
+    // writing rather than the whole process
+    // SAFETY: neither disposition runs any handler code
+    unsafe {
+        let _ = signal(Signal::SIGPIPE, SigHandler::SigIgn);
+    }
+    if let Some(mut input) = child.stdin.take() {
+        let _ = input.write_all(output);
+    }
+    let _ = child.wait();
+    true
+}
@@ -0,0 +1,170 @@
+use nix::sys::termios::{cfmakeraw, tcgetattr, tcsetattr, SetArg, Termios};
+use nucleo_matcher::{
+    pattern::{CaseMatching, Normalization, Pattern},
+    Config, Matcher, Utf32Str,
+};
+use std::{
+    cmp::Reverse,
+    collections::BTreeSet,
+    fs::{File, OpenOptions},
+    io::{self, Read, Write},
+};
+use terminal_size::{terminal_size_of, Height};
+
+/// Rows the prompt and the table's borders and header take up
+const CHROME: usize = 6;
+
+/// What the picker asks to have drawn for the current query
+pub struct View<'a> {
+    /// Indices of the visible matches, best first
+    pub shown: &'a [usize],
+    /// Position of the highlighted match within `shown`
+    pub cursor: usize,
+    pub marked: &'a BTreeSet<usize>,
+}
+
+enum Key {
+    Char(char),
+    Backspace,
+    ClearLine,
+    Up,
+    Down,
+    Tab,
+    Enter,
+    Cancel,
+    Other,
+}
+
+/// The controlling terminal in raw mode, restored when dropped so a panic
+/// doesn't leave the shell unusable
+struct Tty {
+    file: File,
+    original: Termios,
+}
+
+impl Tty {
+    fn open() -> io::Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+        let original = tcgetattr(&file)?;
+        let mut raw = original.clone();
+        cfmakeraw(&mut raw);
+        tcsetattr(&file, SetArg::TCSANOW, &raw)?;
+        // The alternate screen keeps the picker out of the scrollback
+        let mut tty = Tty { file, original };
+        tty.file.write_all(b"\x1b[?1049h")?;
+        Ok(tty)
+    }
+
+    fn height(&self) -> usize {
+        terminal_size_of(&self.file).map_or(24, |(_, Height(height))| height as usize)
+    }
+
+    fn read_key(&mut self) -> io::Result<Key> {
+        // Escape sequences for the arrows arrive in a single read
+        let mut buf = [0; 16];
+        let len = self.file.read(&mut buf)?;
+        Ok(match &buf[..len] {
+            [0x1b] | [0x03] | [] => Key::Cancel,
+            [0x1b, b'[', b'A'] | [0x10] => Key::Up,
+            [0x1b, b'[', b'B'] | [0x0e] => Key::Down,
+            [0x7f] | [0x08] => Key::Backspace,
+            [0x15] => Key::ClearLine,
+            [b'\t'] => Key::Tab,
+            [b'\r'] | [b'\n'] => Key::Enter,
+            bytes => match std::str::from_utf8(bytes)
+                .ok()
+                .and_then(|s| s.chars().next())
+            {
+                Some(ch) if !ch.is_control() => Key::Char(ch),
+                _ => Key::Other,
+            },
+        })
+    }
+
+    fn draw(&mut self, query: &str, body: &str) -> io::Result<()> {
+        // Raw mode turns off the translation of `\n` into `\r\n`
+        let body = body.trim_end().replace('\n', "\r\n");
+        write!(self.file, "\x1b[H\x1b[2J\r\n{}\x1b[1;1H> {}", body, query)?;
+        self.file.flush()
+    }
+}
+
+impl Drop for Tty {
+    fn drop(&mut self) {
+        let _ = self.file.write_all(b"\x1b[?1049l");
+        let _ = tcsetattr(&self.file, SetArg::TCSANOW, &self.original);
+    }
+}
+
+/// Lets the user fuzzy filter `names` by typing, redrawing with `render`
+/// after every key. Returns the indices of the marked entries, or of the
+/// highlighted one when nothing is marked, and `None` when cancelled
+pub fn pick(names: &[String], render: impl Fn(View) -> String) -> io::Result<Option<Vec<usize>>> {
+    let mut tty = Tty::open()?;
+    let mut matcher = Matcher::new(Config::DEFAULT.match_paths());
+    let mut query = String::new();
+    let mut marked = BTreeSet::new();
+    let mut cursor = 0;
+
+    loop {
+        let matches = filter(names, &query, &mut matcher);
+        cursor = cursor.min(matches.len().saturating_sub(1));
+        // Scrolls just far enough to keep the highlighted row on screen
+        let rows = tty.height().saturating_sub(CHROME).max(1);
+        let start = cursor.saturating_sub(rows - 1);
+        let end = (start + rows).min(matches.len());
+        let body = if matches.is_empty() {
+            String::new()
+        } else {
+            render(View {
+                shown: &matches[start..end],
+                cursor: cursor - start,
+                marked: &marked,
+            })
+        };
+        tty.draw(&query, &body)?;
+
+        match tty.read_key()? {
+            Key::Char(ch) => {
+                query.push(ch);
+                cursor = 0;
+            }
+            Key::Backspace => {
+                query.pop();
+                cursor = 0;
+            }
+            Key::ClearLine => {
+                query.clear();
+                cursor = 0;
+            }
+            Key::Up => cursor = cursor.saturating_sub(1),
+            Key::Down => cursor += 1,
+            Key::Tab => {
+                if let Some(&index) = matches.get(cursor) {
+                    if !marked.remove(&index) {
+                        marked.insert(index);
+                    }
+                    cursor += 1;
+                }
+            }
+            Key::Enter if !marked.is_empty() => return Ok(Some(marked.into_iter().collect())),
+            Key::Enter => return Ok(Some(matches.get(cursor).copied().into_iter().collect())),
+            Key::Cancel => return Ok(None),
+            Key::Other => {}
+        }
+    }
+}
+
+/// Indices of the names matching `query`, best match first and otherwise
+/// in listing order
+fn filter(names: &[String], query: &str, matcher: &mut Matcher) -> Vec<usize> {
+    let pattern = Pattern::parse(query, CaseMatching::Smart, Normalization::Smart);
+    let mut buf = Vec::new();
+    let mut scored: Vec<(usize, u32)> = names
+        .iter()
+        .enumerate()
+        .filter_map(|(i, name)| Some((i, pattern.score(Utf32Str::new(name, &mut buf), matcher)?)))
+        .collect();
+    scored.sort_by_key(|&(_, score)| Reverse(score));
+    scored.into_iter().map(|(i, _)| i).collect()
+}
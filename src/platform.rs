@@ -0,0 +1,227 @@
+use crate::FileType;
+use std::{fs, path::Path};
+
+#[cfg(unix)]
+use nix::{
+    sys::{
+        signal::{signal, SigHandler, Signal},
+        stat::{major, minor},
+        statvfs::statvfs,
+    },
+    unistd::{Gid, Group, Uid, User},
+};
+#[cfg(unix)]
+use std::os::unix::fs::{FileTypeExt, MetadataExt, PermissionsExt};
+#[cfg(windows)]
+use std::os::windows::fs::MetadataExt;
+
+/// The numbers `stat` reports on Unix. Platforms without them get a mode
+/// derived from what they do record and zeroes for the rest
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Stat {
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub inode: u64,
+    pub links: u64,
+    pub blocks: u64,
+    pub rdev: u64,
+}
+
+/// Whether entries have a Unix owner and group to show
+pub const HAS_OWNERS: bool = cfg!(unix);
+
+/// Lets a closed pipe end the process quietly like other command line
+/// tools, where Rust ignores `SIGPIPE` and the next `println!` panics
+#[cfg(unix)]
+pub fn default_sigpipe() {
+    // SAFETY: the default disposition doesn't run any handler code
+    unsafe {
+        let _ = signal(Signal::SIGPIPE, SigHandler::SigDfl);
+    }
+}
+
+#[cfg(windows)]
+pub fn default_sigpipe() {}
+
+#[cfg(unix)]
+pub fn stat(meta_data: &fs::Metadata) -> Stat {
+    Stat {
+        mode: meta_data.permissions().mode(),
+        uid: meta_data.uid(),
+        gid: meta_data.gid(),
+        inode: meta_data.ino(),
+        links: meta_data.nlink(),
+        blocks: meta_data.blocks(),
+        rdev: meta_data.rdev(),
+    }
+}
+
+#[cfg(windows)]
+pub fn stat(meta_data: &fs::Metadata) -> Stat {
+    // Close enough for `--perm`, `--octal` and `-F`, which only care about
+    // who can write and what can be entered
+    let mut mode = if meta_data.permissions().readonly() {
+        0o444
+    } else {
+        0o666
+    };
+    if meta_data.is_dir() {
+        mode |= 0o111;
+    }
+    Stat {
+        mode,
+        links: 1,
+        ..Stat::default()
+    }
+}
+
+#[cfg(unix)]
+pub fn file_type(file_type: fs::FileType) -> FileType {
+    if file_type.is_symlink() {
+        FileType::Symlink
+    } else if file_type.is_dir() {
+        FileType::Dir
+    } else if file_type.is_fifo() {
+        FileType::Fifo
+    } else if file_type.is_socket() {
+        FileType::Socket
+    } else if file_type.is_block_device() {
+        FileType::BlockDevice
+    } else if file_type.is_char_device() {
+        FileType::CharDevice
+    } else {
+        FileType::File
+    }
+}
+
+#[cfg(windows)]
+pub fn file_type(file_type: fs::FileType) -> FileType {
+    if file_type.is_symlink() {
+        FileType::Symlink
+    } else if file_type.is_dir() {
+        FileType::Dir
+    } else {
+        FileType::File
+    }
+}
+
+#[cfg(unix)]
+pub fn user_name(uid: u32) -> Option<String> {
+    User::from_uid(Uid::from(uid))
+        .ok()
+        .flatten()
+        .map(|user| user.name)
+}
+
+#[cfg(windows)]
+pub fn user_name(_uid: u32) -> Option<String> {
+    None
+}
+
+#[cfg(unix)]
+pub fn group_name(gid: u32) -> Option<String> {
+    Group::from_gid(Gid::from(gid))
+        .ok()
+        .flatten()
+        .map(|group| group.name)
+}
+
+#[cfg(windows)]
+pub fn group_name(_gid: u32) -> Option<String> {
+    None
+}
+
+#[cfg(unix)]
+pub fn uid_by_name(name: &str) -> Option<u32> {
+    User::from_name(name)
+        .ok()
+        .flatten()
+        .map(|user| user.uid.as_raw())
+}
+
+#[cfg(windows)]
+pub fn uid_by_name(_name: &str) -> Option<u32> {
+    None
+}
+
+#[cfg(unix)]
+pub fn current_uid() -> Option<u32> {
+    Some(Uid::current().as_raw())
+}
+
+#[cfg(windows)]
+pub fn current_uid() -> Option<u32> {
+    None
+}
+
+#[cfg(unix)]
+pub fn has_xattrs(path: &Path) -> bool {
+    xattr::list(path).is_ok_and(|mut attrs| attrs.next().is_some())
+}
+
+#[cfg(windows)]
+pub fn has_xattrs(_path: &Path) -> bool {
+    false
+}
+
+/// Major and minor number of a device
+#[cfg(unix)]
+pub fn device_numbers(rdev: u64) -> (u64, u64) {
+    (major(rdev), minor(rdev))
+}
+
+#[cfg(windows)]
+pub fn device_numbers(_rdev: u64) -> (u64, u64) {
+    (0, 0)
+}
+
+/// Space of the filesystem holding a path, in bytes
+#[derive(Debug, Clone, Copy)]
+pub struct DiskSpace {
+    pub total: u64,
+    pub used: u64,
+    /// What unprivileged users can still write, which leaves out the
+    /// blocks reserved for root
+    pub available: u64,
+}
+
+#[cfg(unix)]
+pub fn disk_space(path: &Path) -> Option<DiskSpace> {
+    let stats = statvfs(path).ok()?;
+    let fragment = stats.fragment_size() as u64;
+    Some(DiskSpace {
+        total: stats.blocks() as u64 * fragment,
+        used: (stats.blocks() - stats.blocks_free()) as u64 * fragment,
+        available: stats.blocks_available() as u64 * fragment,
+    })
+}
+
+#[cfg(windows)]
+pub fn disk_space(_path: &Path) -> Option<DiskSpace> {
+    None
+}
+
+/// `darhsl` attributes like PowerShell's `Mode` column, Windows has no
+/// `rwx` bits to show
+#[cfg(windows)]
+pub fn attributes(meta_data: &fs::Metadata) -> String {
+    const READONLY: u32 = 0x1;
+    const HIDDEN: u32 = 0x2;
+    const SYSTEM: u32 = 0x4;
+    const ARCHIVE: u32 = 0x20;
+    const REPARSE_POINT: u32 = 0x400;
+
+    let attributes = meta_data.file_attributes();
+    [
+        (meta_data.is_dir(), 'd'),
+        (attributes & ARCHIVE != 0, 'a'),
+        (attributes & READONLY != 0, 'r'),
+        (attributes & HIDDEN != 0, 'h'),
+        (attributes & SYSTEM != 0, 's'),
+        (attributes & REPARSE_POINT != 0, 'l'),
+    ]
+    .into_iter()
+    .map(|(set, ch)| if set { ch } else { '-' })
+    .collect()
+}
@@ -0,0 +1,167 @@
+use crate::error_text;
+use owo_colors::{DynColors, Style as TextStyle};
+use std::{env, fs, path::PathBuf};
+use toml::{Table, Value};
+
+/// Style of every colored part of the listing, keyed by what it means rather
+/// than by where it's drawn
+#[derive(Debug, Clone, Copy)]
+pub struct Theme {
+    pub dir: TextStyle,
+    pub file: TextStyle,
+    /// Sizes under a megabyte
+    pub size_small: TextStyle,
+    /// Sizes under a gigabyte
+    pub size_medium: TextStyle,
+    pub size_large: TextStyle,
+    /// Timestamps within the last hour
+    pub age_hour: TextStyle,
+    /// Timestamps within the last day
+    pub age_day: TextStyle,
+    /// Timestamps within the last 30 days
+    pub age_month: TextStyle,
+    pub age_old: TextStyle,
+    pub permission_read: TextStyle,
+    pub permission_write: TextStyle,
+    pub permission_exec: TextStyle,
+    /// Any bit set for the group or others
+    pub permission_other: TextStyle,
+    pub permission_none: TextStyle,
+    /// setuid, setgid and sticky
+    pub permission_special: TextStyle,
+    /// Where sizes stop being small and medium, `None` keeps a megabyte and
+    /// a gigabyte in whichever units are shown
+    pub size_thresholds: Option<SizeThresholds>,
+}
+
+/// Byte sizes from the `[size-thresholds]` table
+#[derive(Debug, Clone, Copy)]
+pub struct SizeThresholds {
+    pub medium: u64,
+    pub large: u64,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        // Bold like the file type indicator in front of the permissions
+        let bold = TextStyle::new().bold();
+        Theme {
+            dir: TextStyle::new().blue().bold(),
+            file: TextStyle::new().white(),
+            size_small: TextStyle::new().green(),
+            size_medium: TextStyle::new().bright_yellow(),
+            size_large: TextStyle::new().bright_red(),
+            age_hour: TextStyle::new().bright_cyan().bold(),
+            age_day: TextStyle::new().bright_blue(),
+            age_month: TextStyle::new().blue(),
+            age_old: TextStyle::new().bright_black(),
+            permission_read: bold.bright_yellow(),
+            permission_write: bold.bright_red(),
+            permission_exec: bold.bright_yellow(),
+            permission_other: bold.green(),
+            permission_none: bold.bright_black(),
+            permission_special: bold.bright_magenta(),
+            size_thresholds: None,
+        }
+    }
+}
+
+impl Theme {
+    /// Reads `nuls/theme.toml` from the config directory, falling back to
+    /// the built in colors for anything it doesn't set
+    pub fn load() -> Self {
+        let mut theme = Theme::default();
+        let Some(path) = config_path() else {
+            return theme;
+        };
+        // Not having a theme at all is the common case
+        let Ok(contents) = fs::read_to_string(&path) else {
+            return theme;
+        };
+
+        match toml::from_str::<Table>(&contents) {
+            Ok(roles) => {
+                for (role, value) in roles {
+                    let result = match (role.as_str(), value) {
+                        ("size-thresholds", Value::Table(table)) => {
+                            parse_thresholds(&table).map(|thresholds| {
+                                theme.size_thresholds = Some(thresholds);
+                            })
+                        }
+                        (_, Value::String(value)) => theme.set(&role, &value),
+                        _ => Err(format!("invalid value for '{}'", role)),
+                    };
+                    if let Err(err) = result {
+                        eprintln!("{}", error_text(format!("{}: {}", path.display(), err)));
+                    }
+                }
+            }
+            Err(err) => eprintln!("{}", error_text(format!("{}: {}", path.display(), err))),
+        }
+        theme
+    }
+
+    fn set(&mut self, role: &str, value: &str) -> Result<(), String> {
+        let slot = match role {
+            "dir" => &mut self.dir,
+            "file" => &mut self.file,
+            "size-small" => &mut self.size_small,
+            "size-medium" => &mut self.size_medium,
+            "size-large" => &mut self.size_large,
+            "age-hour" => &mut self.age_hour,
+            "age-day" => &mut self.age_day,
+            "age-month" => &mut self.age_month,
+            "age-old" => &mut self.age_old,
+            "permission-read" => &mut self.permission_read,
+            "permission-write" => &mut self.permission_write,
+            "permission-exec" => &mut self.permission_exec,
+            "permission-other" => &mut self.permission_other,
+            "permission-none" => &mut self.permission_none,
+            "permission-special" => &mut self.permission_special,
+            _ => return Err(format!("unknown role '{}'", role)),
+        };
+        *slot = parse_style(value)
+            .ok_or_else(|| format!("invalid color '{}' for '{}'", value, role))?;
+        Ok(())
+    }
+}
+
+fn parse_thresholds(table: &Table) -> Result<SizeThresholds, String> {
+    let get = |key: &str| match table.get(key) {
+        Some(Value::Integer(bytes)) if *bytes >= 0 => Ok(*bytes as u64),
+        Some(_) => Err(format!(
+            "'size-thresholds.{}' must be a number of bytes",
+            key
+        )),
+        None => Err(format!("'size-thresholds' is missing '{}'", key)),
+    };
+    let thresholds = SizeThresholds {
+        medium: get("medium")?,
+        large: get("large")?,
+    };
+    if thresholds.medium >= thresholds.large {
+        return Err("'size-thresholds.medium' must be below 'large'".to_string());
+    }
+    Ok(thresholds)
+}
+
+/// Parses a color name such as `bright blue` or `#ff8800`, optionally
+/// prefixed with `bold`
+fn parse_style(value: &str) -> Option<TextStyle> {
+    let (style, color) = match value.trim().strip_prefix("bold") {
+        Some(color) => (TextStyle::new().bold(), color.trim()),
+        None => (TextStyle::new(), value.trim()),
+    };
+    if color.is_empty() {
+        return Some(style);
+    }
+    let color: DynColors = color.parse().ok()?;
+    Some(style.color(color))
+}
+
+fn config_path() -> Option<PathBuf> {
+    let config_dir = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_dir.join("nuls").join("theme.toml"))
+}